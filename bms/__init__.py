@@ -14,25 +14,36 @@ BMSON_FILE_EXTS = (".bmson",)
 CHART_FILE_EXTS = BMS_FILE_EXTS + BMSON_FILE_EXTS
 
 AUDIO_FILE_EXTS = (".flac", ".ogg", ".wav")
-VIDEO_FILE_EXTS = (".mp4", ".mkv", ".avi", ".wmv", ".mpg", ".mpeg")
+VIDEO_FILE_EXTS = (".mp4", ".mkv", ".avi", ".wmv", ".mpg", ".mpeg", ".webm")
 IMAGE_FILE_EXTS = (".jpg", ".png", ".bmp", ".svg")
 MEDIA_FILE_EXTS = AUDIO_FILE_EXTS + VIDEO_FILE_EXTS + IMAGE_FILE_EXTS
 
 
+def get_dir_encoding(dir_path: Path) -> str | None:
+    """For BOFTT: 按照作品编号获取特定编码"""
+    id = dir_path.name.split(".")[0] if "." in dir_path.name else dir_path.name
+    return BOFTT_ID_SPECIFIC_ENCODING_TABLE.get(id)
+
+
+def parse_chart_file(file_path: Path, encoding: str | None = None) -> BMSInfo | None:
+    """按照扩展名解析BMS/BMSON文件，其他文件返回None"""
+    if not file_path.is_file():
+        return None
+    if file_path.name.lower().endswith(BMS_FILE_EXTS):
+        return parse_bms_file(file_path, encoding)
+    if file_path.name.lower().endswith(BMSON_FILE_EXTS):
+        return parse_bmson_file(file_path, encoding)
+    return None
+
+
 def get_dir_bms_list(dir_path: Path) -> list[BMSInfo]:
     """仅寻找该目录第一层的文件"""
     info_list: list[BMSInfo] = []
-    # For BOFTT
-    id = dir_path.name.split(".")[0] if "." in dir_path.name else dir_path.name
-    encoding = BOFTT_ID_SPECIFIC_ENCODING_TABLE.get(id)
+    encoding = get_dir_encoding(dir_path)
     # Scan
     for file_path in dir_path.iterdir():
         # Parse
-        info: BMSInfo | None = None
-        if file_path.name.lower().endswith(BMS_FILE_EXTS) and file_path.is_file():
-            info = parse_bms_file(file_path, encoding)
-        elif file_path.name.lower().endswith(BMSON_FILE_EXTS) and file_path.is_file():
-            info = parse_bmson_file(file_path, encoding)
+        info: BMSInfo | None = parse_chart_file(file_path, encoding)
         # Append
         if info is not None:
             info_list.append(info)
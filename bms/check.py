@@ -0,0 +1,699 @@
+import json
+from collections.abc import Callable
+from dataclasses import dataclass
+from datetime import datetime
+from enum import Enum
+from fractions import Fraction
+from itertools import combinations
+from pathlib import Path
+from typing import Any
+
+from bms import AUDIO_FILE_EXTS, BMS_FILE_EXTS, BMSON_FILE_EXTS, MEDIA_FILE_EXTS, get_dir_encoding
+from bms.parse import (
+    extract_bms_preview_range,
+    match_bms_header,
+    parse_bms_channel_lines,
+    parse_bms_slot_definitions,
+    read_bms_file_str,
+)
+from fs import walk_bms_tree
+
+# 可见物件通道（1P: 11-19, 2P: 21-29）与长条通道（1P: 51-59, 2P: 61-69）
+NOTE_CHANNEL_PREFIXES = ("1", "2", "5", "6")
+
+
+def _iter_bms_files(root_dir: Path) -> list[Path]:
+    """递归查找根目录下的所有BMS文件（不含BMSON）"""
+    return sorted(p for p in walk_bms_tree(root_dir) if p.is_file() and p.name.lower().endswith(BMS_FILE_EXTS))
+
+
+def find_bms_files_with_keysound_count_above(root_dir: Path, threshold: int) -> list[tuple[Path, int]]:
+    """
+    查找#WAVxx定义数量超过threshold的BMS文件。
+    返回 (文件路径, 定义数量) 列表，按数量降序排列。
+    """
+    results: list[tuple[Path, int]] = []
+    for file_path in _iter_bms_files(root_dir):
+        wav_count = len(parse_bms_slot_definitions(read_bms_file_str(file_path), "WAV"))
+        if wav_count > threshold:
+            results.append((file_path, wav_count))
+    results.sort(key=lambda x: -x[1])
+    return results
+
+
+# 两位36进制槽位（01-ZZ）的最大数量
+BMS_MAX_SLOT_COUNT = 36 * 36
+
+
+def find_works_with_excessive_media_files(root_dir: Path, threshold: int) -> list[tuple[Path, int]]:
+    """
+    查找音频文件数量超过threshold（通常为 BMS_MAX_SLOT_COUNT）的作品目录。
+    返回 (作品目录, 音频文件数量) 列表，按数量降序排列。
+    """
+    results: list[tuple[Path, int]] = []
+    for work_dir in sorted(p for p in root_dir.iterdir() if p.is_dir()):
+        audio_count = len([p for p in work_dir.iterdir() if p.is_file() and p.suffix.lower() in AUDIO_FILE_EXTS])
+        if audio_count > threshold:
+            results.append((work_dir, audio_count))
+    results.sort(key=lambda x: -x[1])
+    return results
+
+
+# BMS规范中小节号为三位数（000-999）
+BMS_MAX_MEASURE = 999
+
+
+def detect_measure_overflow(bms_file: Path) -> list[int]:
+    """返回数据行中超过999的小节号（部分编辑器会错误地输出四位数小节号），按升序排列且不重复。"""
+    measures = {
+        channel_line.measure
+        for channel_line in parse_bms_channel_lines(read_bms_file_str(bms_file))
+        if channel_line.measure > BMS_MAX_MEASURE
+    }
+    return sorted(measures)
+
+
+# 小节长度通道，值为相对于4/4的倍率
+MEASURE_LENGTH_CHANNEL = "02"
+
+
+def detect_bms_measure_time_signature_changes(bms_file: Path) -> list[tuple[int, float]]:
+    """
+    解析所有 "#xxx02:倍率" 行，返回 (小节号, 倍率) 列表，1.0为4/4，0.5为2/4。
+    从第0小节到最后一个显式指定的小节，未指定的小节以1.0补齐；没有任何指定时返回空列表。
+    """
+    multipliers: dict[int, float] = {}
+    for channel_line in parse_bms_channel_lines(read_bms_file_str(bms_file)):
+        if channel_line.channel != MEASURE_LENGTH_CHANNEL:
+            continue
+        try:
+            multipliers[channel_line.measure] = float(channel_line.data)
+        except ValueError:
+            continue
+    if len(multipliers) == 0:
+        return []
+    return [(measure, multipliers.get(measure, 1.0)) for measure in range(max(multipliers) + 1)]
+
+
+def find_bms_files_older_than(root_dir: Path, cutoff: datetime) -> list[tuple[Path, datetime]]:
+    """查找修改时间早于cutoff的BMS文件，返回 (文件路径, 修改时间) 列表，按时间升序排列。"""
+    cutoff_timestamp = cutoff.timestamp()
+    results: list[tuple[Path, datetime]] = []
+    for file_path in _iter_bms_files(root_dir):
+        mtime = file_path.stat().st_mtime
+        if mtime < cutoff_timestamp:
+            results.append((file_path, datetime.fromtimestamp(mtime, tz=cutoff.tzinfo)))
+    results.sort(key=lambda x: x[1])
+    return results
+
+
+def detect_bms_file_size_anomalies(root_dir: Path, min_bytes: int, max_bytes: int) -> list[tuple[Path, int]]:
+    """查找大小不在 [min_bytes, max_bytes] 范围内的BMS文件，返回 (文件路径, 文件大小) 列表"""
+    results: list[tuple[Path, int]] = []
+    for file_path in _iter_bms_files(root_dir):
+        size = file_path.stat().st_size
+        if not min_bytes <= size <= max_bytes:
+            results.append((file_path, size))
+    return results
+
+
+@dataclass
+class OverlapInfo:
+    measure: int
+    # 小节内位置，0 <= position < 1
+    position: Fraction
+    # 按可见物件通道表示的轨道，如 "11"
+    lane: str
+    slot_a: str
+    slot_b: str
+
+
+def _get_note_lane(channel: str) -> str | None:
+    if len(channel) != 2 or channel[0] not in NOTE_CHANNEL_PREFIXES or channel[1] not in "123456789":
+        return None
+    player = "1" if channel[0] in ("1", "5") else "2"
+    return f"{player}{channel[1]}"
+
+
+def detect_bms_overlapping_notes(bms_file: Path) -> list[OverlapInfo]:
+    """查找同一轨道、同一时刻存在多个物件的位置，返回所有重叠的物件对。"""
+    notes: dict[tuple[int, str, Fraction], list[str]] = {}
+    for channel_line in parse_bms_channel_lines(read_bms_file_str(bms_file)):
+        lane = _get_note_lane(channel_line.channel)
+        if lane is None:
+            continue
+        objects = channel_line.objects()
+        for i, slot in enumerate(objects):
+            if slot == "00":
+                continue
+            notes.setdefault((channel_line.measure, lane, Fraction(i, len(objects))), []).append(slot)
+
+    overlaps: list[OverlapInfo] = []
+    for (measure, lane, position), slots in sorted(notes.items()):
+        for slot_a, slot_b in combinations(slots, 2):
+            overlaps.append(OverlapInfo(measure, position, lane, slot_a, slot_b))
+    return overlaps
+
+
+def count_bms_notes(file_str: str) -> int:
+    """
+    统计物件数：可见物件（不含#LNOBJ指定的长条终点），长条通道中每对起止计为一个。
+    不处理#RANDOM分支，所有分支中的物件都会被计入。
+    """
+    ln_obj_slots: set[str] = set()
+    for line in file_str.splitlines():
+        ln_obj = match_bms_header(line, "LNOBJ")
+        if ln_obj is not None:
+            ln_obj_slots.add(ln_obj.upper())
+
+    note_count = 0
+    ln_object_counts: dict[str, int] = {}
+    for channel_line in parse_bms_channel_lines(file_str):
+        lane = _get_note_lane(channel_line.channel)
+        if lane is None:
+            continue
+        objects = [slot for slot in channel_line.objects() if slot != "00"]
+        if channel_line.channel[0] in ("5", "6"):
+            ln_object_counts[lane] = ln_object_counts.get(lane, 0) + len(objects)
+        else:
+            note_count += len([slot for slot in objects if slot not in ln_obj_slots])
+    return note_count + sum((count + 1) // 2 for count in ln_object_counts.values())
+
+
+def get_bms_chart_duration(file_str: str) -> float:
+    """
+    计算从开头到最后一个可见物件的时长（秒）。
+    考虑#BPM、小节长度（02通道）与变速（03、08通道），不考虑#STOP；不处理#RANDOM分支。
+    """
+    initial_bpm = 130.0
+    for line in file_str.splitlines():
+        bpm_str = match_bms_header(line, "BPM")
+        if bpm_str is not None:
+            try:
+                initial_bpm = float(bpm_str)
+            except ValueError:
+                pass
+    bpm_defs = parse_bms_slot_definitions(file_str, "BPM")
+
+    measure_lengths: dict[int, float] = {}
+    bpm_changes: dict[int, list[tuple[Fraction, float]]] = {}
+    last_note: tuple[int, Fraction] | None = None
+    for channel_line in parse_bms_channel_lines(file_str):
+        if channel_line.channel == "02":
+            try:
+                measure_lengths[channel_line.measure] = float(channel_line.data)
+            except ValueError:
+                pass
+            continue
+        objects = channel_line.objects()
+        for i, slot in enumerate(objects):
+            if slot == "00":
+                continue
+            position = (channel_line.measure, Fraction(i, len(objects)))
+            bpm: float | None = None
+            if channel_line.channel == "03":
+                bpm = int(slot, 16) if all(c in "0123456789ABCDEF" for c in slot) else None
+            elif channel_line.channel == "08" and slot in bpm_defs:
+                try:
+                    bpm = float(bpm_defs[slot])
+                except ValueError:
+                    bpm = None
+            elif _get_note_lane(channel_line.channel) is not None and (last_note is None or position > last_note):
+                last_note = position
+            if bpm is not None and bpm > 0:
+                bpm_changes.setdefault(channel_line.measure, []).append((position[1], bpm))
+    if last_note is None:
+        return 0.0
+
+    duration = 0.0
+    bpm = initial_bpm
+    for measure in range(last_note[0] + 1):
+        end = last_note[1] if measure == last_note[0] else Fraction(1)
+        beats = measure_lengths.get(measure, 1.0) * 4
+        position = Fraction(0)
+        for change_position, change_bpm in sorted(bpm_changes.get(measure, [])):
+            if change_position > end:
+                break
+            duration += float(change_position - position) * beats * 60 / bpm
+            position, bpm = change_position, change_bpm
+        duration += float(end - position) * beats * 60 / bpm
+    return duration
+
+
+def compute_bms_difficulty_rating(bms_file: Path) -> float:
+    """按LR2的估算方式计算难度：物件数 / 时长（秒） * 60 / 4。无物件时为0。"""
+    file_str = read_bms_file_str(bms_file)
+    duration = get_bms_chart_duration(file_str)
+    if duration <= 0:
+        return 0.0
+    return count_bms_notes(file_str) / duration * 60 / 4
+
+
+def rank_works_by_difficulty_rating(root_dir: Path) -> list[tuple[Path, float]]:
+    """以作品目录中最高的难度估算值排序，从难到易"""
+    ratings: dict[Path, float] = {}
+    for file_path in _iter_bms_files(root_dir):
+        rating = compute_bms_difficulty_rating(file_path)
+        ratings[file_path.parent] = max(rating, ratings.get(file_path.parent, 0.0))
+    return sorted(ratings.items(), key=lambda item: item[1], reverse=True)
+
+
+# 地雷物件通道（1P: D1-D9, 2P: E1-E9）
+MINE_CHANNEL_PREFIXES = ("D", "E")
+
+
+def detect_bms_mine_notes(bms_file: Path) -> bool:
+    """谱面是否在地雷通道中放置了物件"""
+    for channel_line in parse_bms_channel_lines(read_bms_file_str(bms_file)):
+        channel = channel_line.channel
+        if channel[0] not in MINE_CHANNEL_PREFIXES or channel[1] not in "123456789":
+            continue
+        if any(slot != "00" for slot in channel_line.objects()):
+            return True
+    return False
+
+
+def find_charts_with_mine_notes(root_dir: Path) -> list[Path]:
+    """列出包含地雷物件谱面的作品目录"""
+    return sorted({file_path.parent for file_path in _iter_bms_files(root_dir) if detect_bms_mine_notes(file_path)})
+
+
+# 皿（P1/P2），含长条通道
+SCRATCH_CHANNELS = ("16", "26", "56", "66")
+
+
+def detect_bms_scratch_notes(bms_file: Path) -> bool:
+    """谱面是否在皿通道中放置了物件"""
+    for channel_line in parse_bms_channel_lines(read_bms_file_str(bms_file)):
+        if channel_line.channel not in SCRATCH_CHANNELS:
+            continue
+        if any(slot != "00" for slot in channel_line.objects()):
+            return True
+    return False
+
+
+def detect_charts_with_scratch_notes(root_dir: Path) -> list[Path]:
+    """列出包含皿物件谱面的作品目录"""
+    return sorted({file_path.parent for file_path in _iter_bms_files(root_dir) if detect_bms_scratch_notes(file_path)})
+
+
+# 31-39 / 41-49 通道。标准BMS中为1P/2P的隐藏物件，部分扩展规格另作他用
+SOFT_LANDING_CHANNEL_PREFIXES = ("3", "4")
+
+
+def detect_bms_soft_landing_notes(bms_file: Path) -> bool:
+    """谱面是否在31-39、41-49通道中放置了物件，即使用了超出常规可见物件的扩展通道"""
+    for channel_line in parse_bms_channel_lines(read_bms_file_str(bms_file)):
+        channel = channel_line.channel
+        if channel[0] not in SOFT_LANDING_CHANNEL_PREFIXES or channel[1] not in "123456789":
+            continue
+        if any(slot != "00" for slot in channel_line.objects()):
+            return True
+    return False
+
+
+def detect_bms_non_standard_channel_usage(bms_file: Path) -> dict[int, bool]:
+    """
+    返回数据行中使用的所有通道 -> 是否为扩展通道。通道以36进制解析为整数，如 "D1" -> 469。
+    BMS 1.0 仅定义了由两位十进制数字组成的通道（"01"-"99"），含字母的通道（如地雷通道 "D1"、BGA图层2 "0A"）视为扩展通道。
+    """
+    channels: dict[int, bool] = {}
+    for channel_line in parse_bms_channel_lines(read_bms_file_str(bms_file)):
+        channels[int(channel_line.channel, 36)] = not channel_line.channel.isdigit()
+    return channels
+
+
+def detect_bms_keymode(file_str: str, is_pms: bool = False) -> str:
+    """根据使用的物件通道推断键位模式，如 "7K"、"14K"。PMS文件固定为 "9K"。"""
+    if is_pms:
+        return "9K"
+    lanes: set[str] = set()
+    for channel_line in parse_bms_channel_lines(file_str):
+        lane = _get_note_lane(channel_line.channel)
+        if lane is not None and any(slot != "00" for slot in channel_line.objects()):
+            lanes.add(lane)
+    # 18/19、28/29 为7键模式中的第6、7键
+    is_7k = any(lane[1] in ("8", "9") for lane in lanes)
+    if any(lane[0] == "2" for lane in lanes):
+        return "14K" if is_7k else "10K"
+    return "7K" if is_7k else "5K"
+
+
+def validate_bms_total_notes_match_actual(bms_file: Path) -> tuple[int, int] | None:
+    """
+    比较#NOTES声明的物件数与实际物件数。
+    不一致时返回 (声明值, 实际值)；一致或没有#NOTES时返回None。
+    """
+    file_str = read_bms_file_str(bms_file)
+    declared: int | None = None
+    for line in file_str.splitlines():
+        notes = match_bms_header(line, "NOTES")
+        if notes is not None and notes.isdigit():
+            declared = int(notes)
+    if declared is None:
+        return None
+    actual = count_bms_notes(file_str)
+    return (declared, actual) if declared != actual else None
+
+
+@dataclass
+class InvalidHeader:
+    line_number: int
+    key: str
+    value: str
+    reason: str
+
+
+def _check_positive_number(value: str) -> str | None:
+    try:
+        number = float(value)
+    except ValueError:
+        return "not a number"
+    return None if number > 0 else "must be positive"
+
+
+def _check_int_range(value: str, min_value: int, max_value: int) -> str | None:
+    if not value.isdecimal():
+        return "not an integer"
+    return None if min_value <= int(value) <= max_value else f"must be in {min_value}-{max_value}"
+
+
+# 头部命令 -> 检查函数，值合法时返回None，否则返回原因
+BMS_HEADER_VALUE_CHECKS: dict[str, Callable[[str], str | None]] = {
+    "BPM": _check_positive_number,
+    "PLAYLEVEL": lambda value: _check_int_range(value, 0, 99),
+    "DIFFICULTY": lambda value: _check_int_range(value, 0, 5),
+    "TOTAL": _check_positive_number,
+    "RANK": lambda value: _check_int_range(value, 0, 4),
+}
+
+
+def detect_bms_invalid_header_values(bms_file: Path) -> list[InvalidHeader]:
+    """检查#BPM、#PLAYLEVEL、#DIFFICULTY、#TOTAL、#RANK的值是否符合类型与范围，返回所有不合法的行。"""
+    invalid_headers: list[InvalidHeader] = []
+    for line_number, line in enumerate(read_bms_file_str(bms_file).splitlines(), start=1):
+        for key, check in BMS_HEADER_VALUE_CHECKS.items():
+            value = match_bms_header(line, key)
+            if value is None:
+                continue
+            reason = check(value)
+            if reason is not None:
+                invalid_headers.append(InvalidHeader(line_number, key, value, reason))
+    return invalid_headers
+
+
+# BGM通道，同一小节允许多行叠加
+BGM_CHANNEL = "01"
+
+
+@dataclass
+class CollisionInfo:
+    measure: int
+    channel: str
+    # 重复的数据行原文，按出现顺序排列
+    lines: list[str]
+
+
+def detect_bms_channel_collisions(bms_file: Path) -> list[CollisionInfo]:
+    """
+    查找同一小节、同一通道出现多次的数据行（多数解析器只使用最后一行）。
+    BGM通道（01）本身允许多行，不计入。
+    """
+    file_str = read_bms_file_str(bms_file)
+    file_lines = file_str.splitlines()
+    channel_lines: dict[tuple[int, str], list[str]] = {}
+    for channel_line in parse_bms_channel_lines(file_str):
+        if channel_line.channel == BGM_CHANNEL:
+            continue
+        key = (channel_line.measure, channel_line.channel)
+        channel_lines.setdefault(key, []).append(file_lines[channel_line.line_number - 1].strip())
+    return [
+        CollisionInfo(measure, channel, lines)
+        for (measure, channel), lines in sorted(channel_lines.items())
+        if len(lines) > 1
+    ]
+
+
+class MediaFileLookup:
+    """作品目录中的文件索引，用于不区分大小写地检查引用的文件是否存在"""
+
+    def __init__(self, work_dir: Path) -> None:
+        self.file_paths: dict[str, Path] = {}
+        self.audio_stem_paths: dict[str, Path] = {}
+        for file_path in walk_bms_tree(work_dir):
+            if not file_path.is_file():
+                continue
+            key = file_path.relative_to(work_dir).as_posix().lower()
+            self.file_paths[key] = file_path
+            if file_path.suffix.lower() in AUDIO_FILE_EXTS:
+                self.audio_stem_paths[key[: -len(file_path.suffix)]] = file_path
+
+    def get_path(self, name: str) -> Path | None:
+        """返回引用名对应的实际文件路径（不尝试其他音频格式）"""
+        return self.file_paths.get(name.strip().replace("\\", "/").lower())
+
+    def get_audio_path(self, name: str) -> Path | None:
+        """返回引用名对应的实际文件路径，找不到时尝试同名的其他音频格式"""
+        path = self.get_path(name)
+        if path is not None:
+            return path
+        key = name.strip().replace("\\", "/").lower()
+        # 播放器会尝试同名的其他音频格式，如 .wav -> .flac/.ogg
+        stem_key = key.rsplit(".", 1)[0] if "." in key.rsplit("/", 1)[-1] else key
+        return self.audio_stem_paths.get(stem_key)
+
+    def exists(self, name: str) -> bool:
+        return self.get_audio_path(name) is not None
+
+
+def detect_bmson_missing_sound_channels(bmson_file: Path) -> list[str]:
+    """返回bmson的sound_channels中，在磁盘上找不到对应文件的name列表（不区分大小写）"""
+    bmson_info = json.loads(read_bms_file_str(bmson_file))
+    lookup = MediaFileLookup(bmson_file.parent)
+    missing_names: list[str] = []
+    for sound_channel in bmson_info.get("sound_channels") or []:
+        name = sound_channel.get("name")
+        if not isinstance(name, str) or len(name) == 0:
+            continue
+        if not lookup.exists(name) and name not in missing_names:
+            missing_names.append(name)
+    return missing_names
+
+
+def _object_schema(required: list[str], **properties: dict[str, Any]) -> dict[str, Any]:
+    return {"type": "object", "required": required, "properties": properties}
+
+
+def _array_schema(items: dict[str, Any]) -> dict[str, Any]:
+    return {"type": "array", "items": items}
+
+
+_STRING: dict[str, Any] = {"type": "string"}
+_INTEGER: dict[str, Any] = {"type": "integer"}
+_NUMBER: dict[str, Any] = {"type": "number"}
+_BOOLEAN: dict[str, Any] = {"type": "boolean"}
+_BGA_EVENT = _object_schema(["y", "id"], y=_INTEGER, id=_INTEGER)
+
+# bmson 1.0.0 的结构（仅包含本仓库校验所需的 type/required/properties/items）
+BMSON_SCHEMA = _object_schema(
+    ["version", "info"],
+    version=_STRING,
+    info=_object_schema(
+        ["title", "artist", "genre", "level", "init_bpm"],
+        title=_STRING,
+        subtitle=_STRING,
+        artist=_STRING,
+        subartists=_array_schema(_STRING),
+        genre=_STRING,
+        mode_hint=_STRING,
+        chart_name=_STRING,
+        level=_INTEGER,
+        init_bpm=_NUMBER,
+        judge_rank=_NUMBER,
+        total=_NUMBER,
+        back_image=_STRING,
+        eyecatch_image=_STRING,
+        banner_image=_STRING,
+        preview_music=_STRING,
+        resolution=_INTEGER,
+    ),
+    lines=_array_schema(_object_schema(["y"], y=_INTEGER)),
+    bpm_events=_array_schema(_object_schema(["y", "bpm"], y=_INTEGER, bpm=_NUMBER)),
+    stop_events=_array_schema(_object_schema(["y", "duration"], y=_INTEGER, duration=_INTEGER)),
+    sound_channels=_array_schema(
+        _object_schema(
+            ["name", "notes"],
+            name=_STRING,
+            notes=_array_schema(_object_schema(["y"], x=_INTEGER, y=_INTEGER, l=_INTEGER, c=_BOOLEAN)),
+        )
+    ),
+    bga=_object_schema(
+        [],
+        bga_header=_array_schema(_object_schema(["id", "name"], id=_INTEGER, name=_STRING)),
+        bga_events=_array_schema(_BGA_EVENT),
+        layer_events=_array_schema(_BGA_EVENT),
+        poor_events=_array_schema(_BGA_EVENT),
+    ),
+)
+
+_SCHEMA_TYPE_CHECKS: dict[str, Callable[[Any], bool]] = {
+    "object": lambda value: isinstance(value, dict),
+    "array": lambda value: isinstance(value, list),
+    "string": lambda value: isinstance(value, str),
+    "integer": lambda value: isinstance(value, int) and not isinstance(value, bool),
+    "number": lambda value: isinstance(value, int | float) and not isinstance(value, bool),
+    "boolean": lambda value: isinstance(value, bool),
+}
+
+
+@dataclass
+class SchemaError:
+    # JSON Pointer，如 "/info/level"、"/sound_channels/0/notes/3/y"
+    pointer: str
+    message: str
+
+
+def _validate_schema(value: Any, schema: dict[str, Any], pointer: str, errors: list[SchemaError]) -> None:
+    if not _SCHEMA_TYPE_CHECKS[schema["type"]](value):
+        errors.append(SchemaError(pointer, f"expected {schema['type']}, got {type(value).__name__}"))
+        return
+    if schema["type"] == "object":
+        for key in schema["required"]:
+            if key not in value:
+                errors.append(SchemaError(pointer, f"missing required property {key!r}"))
+        for key, sub_schema in schema["properties"].items():
+            # x为null时表示BGM物件
+            if key in value and not (key == "x" and value[key] is None):
+                _validate_schema(value[key], sub_schema, f"{pointer}/{key}", errors)
+    elif schema["type"] == "array":
+        for i, item in enumerate(value):
+            _validate_schema(item, schema["items"], f"{pointer}/{i}", errors)
+
+
+def validate_bmson_schema(bmson_file: Path) -> list[SchemaError]:
+    """按bmson 1.0.0的结构校验文件，返回所有错误；无法解析为JSON时返回一个指向根的错误"""
+    try:
+        bmson_info = json.loads(read_bms_file_str(bmson_file))
+    except json.JSONDecodeError as e:
+        return [SchemaError("", f"invalid JSON: {e}")]
+    errors: list[SchemaError] = []
+    _validate_schema(bmson_info, BMSON_SCHEMA, "", errors)
+    return errors
+
+
+def batch_validate_bmson(root_dir: Path) -> dict[Path, list[SchemaError]]:
+    """校验根目录下所有bmson文件，只返回存在错误的文件"""
+    results: dict[Path, list[SchemaError]] = {}
+    for file_path in sorted(walk_bms_tree(root_dir)):
+        if not file_path.is_file() or not file_path.name.lower().endswith(BMSON_FILE_EXTS):
+            continue
+        errors = validate_bmson_schema(file_path)
+        if len(errors) > 0:
+            results[file_path] = errors
+    return results
+
+
+# 引用单个文件的头部命令
+BMS_FILE_REF_HEADERS = ("STAGEFILE", "BANNER", "BACKBMP", "PREVIEW")
+
+
+def _get_work_dir_referenced_names(work_dir: Path) -> list[str] | None:
+    """作品目录中所有谱面引用的文件名；没有谱面时返回None"""
+    encoding = get_dir_encoding(work_dir)
+    names: list[str] = []
+    has_chart = False
+    for file_path in work_dir.iterdir():
+        if not file_path.is_file():
+            continue
+        file_name = file_path.name.lower()
+        if file_name.endswith(BMS_FILE_EXTS):
+            has_chart = True
+            file_str = read_bms_file_str(file_path, encoding)
+            for command in ("WAV", "BMP"):
+                names.extend(parse_bms_slot_definitions(file_str, command).values())
+            for line in file_str.splitlines():
+                for key in BMS_FILE_REF_HEADERS:
+                    value = match_bms_header(line, key)
+                    if value:
+                        names.append(value)
+        elif file_name.endswith(BMSON_FILE_EXTS):
+            has_chart = True
+            bmson_info = json.loads(read_bms_file_str(file_path, encoding))
+            info = bmson_info.get("info") or {}
+            names.extend(info.get(key) or "" for key in ("eyecatch_image", "title_image", "banner_image", "back_image"))
+            names.extend(channel.get("name") or "" for channel in bmson_info.get("sound_channels") or [])
+            names.extend(header.get("name") or "" for header in (bmson_info.get("bga") or {}).get("bga_header") or [])
+    return names if has_chart else None
+
+
+def report_dead_media_in_work_dirs(root_dir: Path) -> dict[Path, list[Path]]:
+    """
+    对根目录下的每个作品目录，列出没有被任何谱面引用的媒体文件（音频、图片、视频）。
+    引用 "a.wav" 时，同名的 "a.ogg"、"a.flac" 也视为被引用。只返回存在此类文件的作品目录。
+    """
+    results: dict[Path, list[Path]] = {}
+    for work_dir in sorted(p for p in root_dir.iterdir() if p.is_dir()):
+        names = _get_work_dir_referenced_names(work_dir)
+        if names is None:
+            continue
+        ref_keys = {name.strip().replace("\\", "/").lower() for name in names if len(name.strip()) > 0}
+        ref_stem_keys = {key.rsplit(".", 1)[0] for key in ref_keys}
+        dead_files: list[Path] = []
+        for file_path in sorted(walk_bms_tree(work_dir)):
+            if not file_path.is_file() or file_path.suffix.lower() not in MEDIA_FILE_EXTS:
+                continue
+            key = file_path.relative_to(work_dir).as_posix().lower()
+            if key in ref_keys:
+                continue
+            if file_path.suffix.lower() in AUDIO_FILE_EXTS and key[: -len(file_path.suffix)] in ref_stem_keys:
+                continue
+            dead_files.append(file_path)
+        if len(dead_files) > 0:
+            results[work_dir] = dead_files
+    return results
+
+
+class PlayerTarget(Enum):
+    Beatoraja = 0
+    Lr2 = 1
+
+    @property
+    def preview_ext(self) -> str:
+        """该播放器期望的预览音频扩展名"""
+        return ".ogg" if self == PlayerTarget.Beatoraja else ".wav"
+
+
+def check_bms_preview_audio_format(root_dir: Path, player: PlayerTarget) -> list[tuple[Path, str]]:
+    """
+    读取各作品目录中谱面的#PREVIEW，返回预览音频扩展名与目标播放器不符的 (作品目录, 实际扩展名) 列表。
+    """
+    results: list[tuple[Path, str]] = []
+    for work_dir in sorted(p for p in root_dir.iterdir() if p.is_dir()):
+        encoding = get_dir_encoding(work_dir)
+        for file_path in sorted(work_dir.iterdir()):
+            if not file_path.is_file() or not file_path.name.lower().endswith(BMS_FILE_EXTS):
+                continue
+            preview = extract_bms_preview_range(file_path, encoding)
+            if preview is None:
+                continue
+            found_ext = preview[0].suffix.lower()
+            if found_ext != player.preview_ext:
+                results.append((work_dir, found_ext))
+            break
+    return results
+
+
+def detect_bms_bga_missing_bmp00(root_dir: Path) -> list[Path]:
+    """列出没有任何BMS文件定义非空#BMP00（MISS时显示的图片）的作品目录"""
+    results: list[Path] = []
+    for work_dir in sorted(p for p in root_dir.iterdir() if p.is_dir()):
+        encoding = get_dir_encoding(work_dir)
+        bms_files = [p for p in work_dir.iterdir() if p.is_file() and p.name.lower().endswith(BMS_FILE_EXTS)]
+        if len(bms_files) == 0:
+            continue
+        if not any(
+            len(parse_bms_slot_definitions(read_bms_file_str(bms_file, encoding), "BMP").get("00", "")) > 0
+            for bms_file in bms_files
+        ):
+            results.append(work_dir)
+    return results
@@ -0,0 +1,483 @@
+import csv
+import hashlib
+import json
+import sqlite3
+import unicodedata
+from collections.abc import Iterator
+from contextlib import closing
+from dataclasses import dataclass
+from datetime import UTC, datetime
+from enum import Enum
+from pathlib import Path, PureWindowsPath
+from xml.etree import ElementTree
+
+from bms import BMS_FILE_EXTS, get_dir_bms_info, get_dir_encoding, parse_chart_file
+from bms.check import MediaFileLookup, count_bms_notes, detect_bms_keymode
+from bms.parse import (
+    BMSInfo,
+    detect_bms_subartist,
+    extract_bms_preview_range,
+    match_bms_header,
+    parse_bms_channel_lines,
+    parse_bms_slot_definitions,
+    read_bms_file_str,
+)
+from media.audio import read_wav_format
+
+# 查询结果中的值，均可直接序列化为JSON
+CollectionValue = str | int | float | None
+
+BMS_INDEX_DB_NAME = "bms_index.db"
+
+_CHARTS_TABLE_SQL = """
+CREATE TABLE charts (
+    path TEXT PRIMARY KEY,
+    work_dir TEXT NOT NULL,
+    title TEXT NOT NULL,
+    artist TEXT NOT NULL,
+    genre TEXT NOT NULL,
+    difficulty INTEGER NOT NULL,
+    level INTEGER NOT NULL,
+    bpm REAL NOT NULL,
+    subartist TEXT
+)
+"""
+
+
+def _iter_collection_charts(root_dir: Path) -> Iterator[tuple[Path, Path, BMSInfo, str | None]]:
+    """遍历根目录下各作品目录中的谱面，产出 (作品目录, 谱面路径, 谱面信息, 编码)"""
+    for work_dir in root_dir.iterdir():
+        if not work_dir.is_dir():
+            continue
+        encoding = get_dir_encoding(work_dir)
+        for file_path in work_dir.iterdir():
+            info = parse_chart_file(file_path, encoding)
+            if info is None:
+                continue
+            yield work_dir, file_path, info, encoding
+
+
+def index_bms_collection(root_dir: Path, db_path: Path) -> int:
+    """
+    扫描根目录下各作品目录中的谱面文件，将元数据写入SQLite数据库的charts表。
+    每次调用都会重建该表。
+
+    :return: 写入的谱面数量
+    """
+    rows: list[tuple[str, str, str, str, str, int, int, float, str | None]] = []
+    for work_dir, file_path, info, encoding in _iter_collection_charts(root_dir):
+        subartist: str | None = None
+        if file_path.name.lower().endswith(BMS_FILE_EXTS):
+            subartist = detect_bms_subartist(file_path, encoding)
+        rows.append(
+            (
+                str(file_path),
+                str(work_dir),
+                info.title,
+                info.artist,
+                info.genre,
+                info.difficulty.value,
+                info.playlevel,
+                info.bpm,
+                subartist,
+            )
+        )
+
+    with closing(sqlite3.connect(db_path)) as conn:
+        conn.execute("DROP TABLE IF EXISTS charts")
+        conn.execute(_CHARTS_TABLE_SQL)
+        conn.executemany("INSERT OR REPLACE INTO charts VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)", rows)
+        conn.commit()
+    return len(rows)
+
+
+def _open_db_read_only(db_path: Path) -> sqlite3.Connection:
+    if not db_path.is_file():
+        raise FileNotFoundError(f"Database not found: {db_path}")
+    uri = f"{db_path.resolve().as_uri()}?mode=ro"
+    return sqlite3.connect(uri, uri=True)
+
+
+def query_bms_collection(db_path: Path, sql: str) -> list[dict[str, CollectionValue]]:
+    """
+    以只读方式对索引数据库执行SQL查询。
+    例：SELECT path FROM charts WHERE bpm > 180 AND level = 12
+
+    :return: 结果行列表，每行以列名为键
+    """
+    with closing(_open_db_read_only(db_path)) as conn:
+        conn.row_factory = sqlite3.Row
+        rows: list[sqlite3.Row] = conn.execute(sql).fetchall()
+    return [{key: row[key] for key in row.keys()} for row in rows]
+
+
+def _normalize_genre(genre: str) -> str:
+    return unicodedata.normalize("NFC", genre.strip())
+
+
+def list_all_genres(root_dir: Path) -> list[str]:
+    """列出根目录下所有谱面中出现过的#GENRE，按字典序排列。"""
+    genres: set[str] = set()
+    for _work_dir, _file_path, info, _encoding in _iter_collection_charts(root_dir):
+        genre = _normalize_genre(info.genre)
+        if len(genre) > 0:
+            genres.add(genre)
+    return sorted(genres)
+
+
+def count_works_per_genre(root_dir: Path) -> dict[str, int]:
+    """统计每个#GENRE对应的作品目录数量，按字典序排列。"""
+    genre_work_dirs: dict[str, set[Path]] = {}
+    for work_dir, _file_path, info, _encoding in _iter_collection_charts(root_dir):
+        genre = _normalize_genre(info.genre)
+        if len(genre) == 0:
+            continue
+        genre_work_dirs.setdefault(genre, set()).add(work_dir)
+    return {genre: len(genre_work_dirs[genre]) for genre in sorted(genre_work_dirs)}
+
+
+def compute_work_dir_fingerprint(work_dir: Path) -> bytes:
+    """
+    根据各BMS文件的#TITLE、#ARTIST和数据行（不含注释）计算SHA-256，与文件夹名无关。
+    同一作品的两个副本，即使文件夹名不同，指纹也相同。
+    """
+    encoding = get_dir_encoding(work_dir)
+    chart_texts: list[str] = []
+    for file_path in work_dir.iterdir():
+        if not file_path.is_file() or not file_path.name.lower().endswith(BMS_FILE_EXTS):
+            continue
+        file_str = read_bms_file_str(file_path, encoding)
+        title = ""
+        artist = ""
+        for line in file_str.splitlines():
+            title = match_bms_header(line, "TITLE") or title
+            artist = match_bms_header(line, "ARTIST") or artist
+        data_lines = sorted(
+            f"#{channel_line.measure:03d}{channel_line.channel}:{channel_line.data}"
+            for channel_line in parse_bms_channel_lines(file_str)
+        )
+        chart_texts.append("\n".join([title, artist, *data_lines]))
+    h = hashlib.sha256()
+    for chart_text in sorted(chart_texts):
+        h.update(chart_text.encode("utf-8"))
+        h.update(b"\0")
+    return h.digest()
+
+
+def find_duplicate_works(root_dir: Path) -> list[list[Path]]:
+    """按内容指纹查找根目录下重复的作品目录，返回每组重复的目录列表。"""
+    fingerprint_dirs: dict[bytes, list[Path]] = {}
+    for work_dir in sorted(root_dir.iterdir()):
+        if not work_dir.is_dir():
+            continue
+        if not any(p.is_file() and p.name.lower().endswith(BMS_FILE_EXTS) for p in work_dir.iterdir()):
+            continue
+        fingerprint_dirs.setdefault(compute_work_dir_fingerprint(work_dir), []).append(work_dir)
+    return [dirs for dirs in fingerprint_dirs.values() if len(dirs) > 1]
+
+
+class BmsHeaderKey(Enum):
+    Title = "title"
+    Artist = "artist"
+    Genre = "genre"
+    Bpm = "bpm"
+    Level = "level"
+    Keymode = "keymode"
+    NotesCount = "notes"
+
+
+def _get_chart_keymode_and_notes(file_path: Path, encoding: str | None) -> tuple[str, int]:
+    file_str = read_bms_file_str(file_path, encoding)
+    if file_path.name.lower().endswith(BMS_FILE_EXTS):
+        return detect_bms_keymode(file_str, file_path.suffix.lower() == ".pms"), count_bms_notes(file_str)
+    # bmson：x为0或不存在的音符为BGM
+    bmson_info = json.loads(file_str)
+    mode_hint = (bmson_info.get("info") or {}).get("mode_hint") or "beat-7k"
+    notes_count = sum(
+        1
+        for sound_channel in bmson_info.get("sound_channels") or []
+        for note in sound_channel.get("notes") or []
+        if note.get("x")
+    )
+    return str(mode_hint), notes_count
+
+
+def export_bms_to_csv(root_dir: Path, output_csv: Path, fields: list[BmsHeaderKey]) -> int:
+    """
+    将根目录下各作品目录中的谱面信息导出为CSV（UTF-8 BOM，便于Excel打开）。
+    第一列为相对于根目录的路径，其后按 fields 的顺序输出。
+
+    :return: 导出的谱面数量
+    """
+    count = 0
+    with output_csv.open("w", encoding="utf-8-sig", newline="") as f:
+        writer = csv.writer(f)
+        writer.writerow(["path", *(key.value for key in fields)])
+        for _work_dir, file_path, info, encoding in _iter_collection_charts(root_dir):
+            keymode, notes_count = "", 0
+            if BmsHeaderKey.Keymode in fields or BmsHeaderKey.NotesCount in fields:
+                keymode, notes_count = _get_chart_keymode_and_notes(file_path, encoding)
+            values: dict[BmsHeaderKey, CollectionValue] = {
+                BmsHeaderKey.Title: info.title,
+                BmsHeaderKey.Artist: info.artist,
+                BmsHeaderKey.Genre: info.genre,
+                BmsHeaderKey.Bpm: info.bpm,
+                BmsHeaderKey.Level: info.playlevel,
+                BmsHeaderKey.Keymode: keymode,
+                BmsHeaderKey.NotesCount: notes_count,
+            }
+            writer.writerow([file_path.relative_to(root_dir).as_posix(), *(values[key] for key in fields)])
+            count += 1
+    return count
+
+
+# SQL导入用CSV的列：(列名, SQLite类型)
+SQL_IMPORT_COLUMNS: list[tuple[str, str]] = [
+    ("path", "TEXT"),
+    ("work_dir", "TEXT"),
+    ("title", "TEXT"),
+    ("artist", "TEXT"),
+    ("genre", "TEXT"),
+    ("difficulty", "INTEGER"),
+    ("level", "INTEGER"),
+    ("bpm", "REAL"),
+    ("keymode", "TEXT"),
+    ("notes", "INTEGER"),
+    ("modified_at", "TEXT"),
+]
+
+
+def export_bms_for_sql_import(root_dir: Path, output_csv: Path) -> int:
+    """
+    导出便于TablePlus等SQL工具直接导入的CSV：UTF-8（无BOM），列名为小写snake_case，
+    修改时间为ISO 8601（UTC）。同时在旁边写入 "{文件名}.schema.sql"，其中为各列带类型的CREATE TABLE语句。
+
+    :return: 导出的谱面数量
+    """
+    count = 0
+    with output_csv.open("w", encoding="utf-8", newline="") as f:
+        writer = csv.writer(f)
+        writer.writerow([name for name, _type in SQL_IMPORT_COLUMNS])
+        for work_dir, file_path, info, encoding in _iter_collection_charts(root_dir):
+            keymode, notes_count = _get_chart_keymode_and_notes(file_path, encoding)
+            modified_at = datetime.fromtimestamp(file_path.stat().st_mtime, tz=UTC).isoformat(timespec="seconds")
+            writer.writerow(
+                [
+                    file_path.relative_to(root_dir).as_posix(),
+                    work_dir.name,
+                    info.title,
+                    info.artist,
+                    info.genre,
+                    info.difficulty.value,
+                    info.playlevel,
+                    info.bpm,
+                    keymode,
+                    notes_count,
+                    modified_at,
+                ]
+            )
+            count += 1
+    columns_sql = ",\n".join(f"    {name} {sql_type}" for name, sql_type in SQL_IMPORT_COLUMNS)
+    schema_sql = f"CREATE TABLE {output_csv.stem.lower().replace(' ', '_')} (\n{columns_sql}\n);\n"
+    output_csv.with_name(f"{output_csv.name}.schema.sql").write_text(schema_sql, encoding="utf-8")
+    return count
+
+
+def export_beatoraja_folder_config(root_dir: Path, output_json: Path) -> None:
+    """
+    按照根目录当前的直接子目录生成beatoraja的文件夹定义JSON：
+    每个子目录为一项 {"name": 目录名, "path": 绝对路径}，按名称排序。
+    """
+    folders = [
+        {"name": dir_path.name, "path": str(dir_path.resolve())}
+        for dir_path in sorted(root_dir.iterdir())
+        if dir_path.is_dir()
+    ]
+    with output_json.open("w", encoding="utf-8") as f:
+        json.dump(folders, f, ensure_ascii=False, indent=2)
+
+
+@dataclass
+class SongDatabaseEntry:
+    # 作品目录名（LR2中为谱面路径的上一级目录名）
+    work_dir_name: str
+    title: str
+    artist: str
+    in_database: bool
+    on_disk: bool
+
+
+def import_lr2_song_database(db_path: Path, root_dir: Path) -> list[SongDatabaseEntry]:
+    """
+    读取LR2的歌曲数据库（song.db）中的song表，按作品目录名与根目录下的作品目录对照。
+    返回仅存在于数据库中（对应的包缺失）或仅存在于磁盘上（尚未被LR2读取）的作品。
+    """
+    db_entries: dict[str, SongDatabaseEntry] = {}
+    with closing(_open_db_read_only(db_path)) as conn:
+        rows: list[tuple[str, str, str]] = conn.execute("SELECT path, title, artist FROM song").fetchall()
+    for path, title, artist in rows:
+        # LR2使用Windows路径，如 ".\\BMS\\pack\\work\\chart.bms"
+        work_dir_name = PureWindowsPath(path).parent.name
+        if len(work_dir_name) > 0 and work_dir_name not in db_entries:
+            db_entries[work_dir_name] = SongDatabaseEntry(work_dir_name, title or "", artist or "", True, False)
+
+    disk_dir_names = {p.name for p in root_dir.iterdir() if p.is_dir()}
+    results = [entry for name, entry in sorted(db_entries.items()) if name not in disk_dir_names]
+    for work_dir_name in sorted(disk_dir_names - db_entries.keys()):
+        info = get_dir_bms_info(root_dir / work_dir_name)
+        if info is None:
+            continue
+        results.append(SongDatabaseEntry(work_dir_name, info.title, info.artist, False, True))
+    return results
+
+
+def _get_work_dir_preview_audio(work_dir: Path, fallback_to_wav01: bool = True) -> Path | None:
+    """作品目录的预览音频：优先使用#PREVIEW，其次（fallback_to_wav01时）使用#WAV01"""
+    encoding = get_dir_encoding(work_dir)
+    lookup = MediaFileLookup(work_dir)
+    bms_files = sorted(p for p in work_dir.iterdir() if p.is_file() and p.name.lower().endswith(BMS_FILE_EXTS))
+    for bms_file in bms_files:
+        preview = extract_bms_preview_range(bms_file, encoding)
+        if preview is not None:
+            preview_path = lookup.get_audio_path(preview[0].as_posix())
+            if preview_path is not None:
+                return preview_path
+    if not fallback_to_wav01:
+        return None
+    for bms_file in bms_files:
+        wav01 = parse_bms_slot_definitions(read_bms_file_str(bms_file, encoding), "WAV").get("01")
+        if wav01 is not None:
+            wav01_path = lookup.get_audio_path(wav01)
+            if wav01_path is not None:
+                return wav01_path
+    return None
+
+
+def _get_audio_duration(file_path: Path) -> int:
+    """音频时长（秒），仅支持WAV，无法获取时返回-1"""
+    wav_format = read_wav_format(file_path) if file_path.suffix.lower() == ".wav" else None
+    if wav_format is None or wav_format.sample_rate == 0 or wav_format.block_align == 0:
+        return -1
+    return round(wav_format.data_size / wav_format.block_align / wav_format.sample_rate)
+
+
+def export_collection_as_m3u(root_dir: Path, output_m3u: Path) -> int:
+    """
+    为根目录下的每个作品目录写入一条扩展M3U条目，指向其预览音频（#PREVIEW，没有时为#WAV01）。
+
+    :return: 写入的条目数
+    """
+    entry_count = 0
+    with output_m3u.open("w", encoding="utf-8") as f:
+        f.write("#EXTM3U\n")
+        for work_dir in sorted(p for p in root_dir.iterdir() if p.is_dir()):
+            audio_path = _get_work_dir_preview_audio(work_dir)
+            info = get_dir_bms_info(work_dir)
+            if audio_path is None or info is None:
+                continue
+            f.write(f"#EXTINF:{_get_audio_duration(audio_path)},{info.title} - {info.artist}\n")
+            f.write(f"{audio_path.resolve()}\n")
+            entry_count += 1
+    return entry_count
+
+
+@dataclass
+class BmsSearchIndex:
+    """倒排索引：词 -> 包含该词的谱面路径列表"""
+
+    terms: dict[str, list[Path]]
+
+
+def _is_cjk_char(char: str) -> bool:
+    return unicodedata.name(char, "").startswith(("CJK", "HIRAGANA", "KATAKANA", "HANGUL"))
+
+
+def tokenize_search_text(text: str) -> list[str]:
+    """按空白分词，CJK字符各自成为一个词；统一为NFKC并忽略大小写"""
+    tokens: list[str] = []
+    for word in unicodedata.normalize("NFKC", text).casefold().split():
+        current = ""
+        for char in word:
+            if _is_cjk_char(char):
+                if len(current) > 0:
+                    tokens.append(current)
+                    current = ""
+                tokens.append(char)
+            else:
+                current += char
+        if len(current) > 0:
+            tokens.append(current)
+    return tokens
+
+
+def build_bms_search_index(root_dir: Path) -> BmsSearchIndex:
+    """以谱面的#TITLE、#ARTIST、#GENRE建立倒排索引"""
+    terms: dict[str, list[Path]] = {}
+    for _work_dir, file_path, info, _encoding in _iter_collection_charts(root_dir):
+        for token in set(tokenize_search_text(f"{info.title} {info.artist} {info.genre}")):
+            terms.setdefault(token, []).append(file_path)
+    return BmsSearchIndex(terms=terms)
+
+
+def search_bms_index(index: BmsSearchIndex, query: str) -> list[Path]:
+    """返回包含查询中所有词的谱面路径（AND检索），按路径排序"""
+    result: set[Path] | None = None
+    for token in tokenize_search_text(query):
+        paths = set(index.terms.get(token, []))
+        result = paths if result is None else result & paths
+    return sorted(result or set())
+
+
+def export_missing_wav_report(root_dir: Path, output: Path) -> int:
+    """
+    检查根目录下各作品目录中BMS文件的#WAVxx、#BMPxx声明，引用的文件不存在（不区分大小写）时，
+    写入CSV（work_dir, bms_file, slot, expected_file）。#WAV引用允许同名的其他音频格式，与播放器行为一致。
+
+    :return: 缺失的引用数量
+    """
+    missing_count = 0
+    with output.open("w", encoding="utf-8-sig", newline="") as f:
+        writer = csv.writer(f)
+        writer.writerow(["work_dir", "bms_file", "slot", "expected_file"])
+        for work_dir in sorted(p for p in root_dir.iterdir() if p.is_dir()):
+            encoding = get_dir_encoding(work_dir)
+            lookup = MediaFileLookup(work_dir)
+            for bms_file in sorted(work_dir.iterdir()):
+                if not bms_file.is_file() or not bms_file.name.lower().endswith(BMS_FILE_EXTS):
+                    continue
+                file_str = read_bms_file_str(bms_file, encoding)
+                for command in ("WAV", "BMP"):
+                    for slot, name in parse_bms_slot_definitions(file_str, command).items():
+                        if len(name) == 0:
+                            continue
+                        path = lookup.get_audio_path(name) if command == "WAV" else lookup.get_path(name)
+                        if path is not None:
+                            continue
+                        writer.writerow([work_dir.name, bms_file.name, f"{command}{slot}", name])
+                        missing_count += 1
+    return missing_count
+
+
+def generate_bms_preview_playlist(root_dir: Path, output_fpl: Path) -> int:
+    """
+    将各作品目录#PREVIEW指定的预览音频写入播放列表，以#TITLE、#ARTIST作为曲目信息。
+    foobar2000原生的.fpl为未公开的二进制格式，此处写入的是foobar2000可导入的XSPF（XML）内容。
+
+    :return: 写入的条目数
+    """
+    playlist = ElementTree.Element("playlist", version="1", xmlns="http://xspf.org/ns/0/")
+    track_list = ElementTree.SubElement(playlist, "trackList")
+    entry_count = 0
+    for work_dir in sorted(p for p in root_dir.iterdir() if p.is_dir()):
+        audio_path = _get_work_dir_preview_audio(work_dir, fallback_to_wav01=False)
+        info = get_dir_bms_info(work_dir)
+        if audio_path is None or info is None:
+            continue
+        track = ElementTree.SubElement(track_list, "track")
+        ElementTree.SubElement(track, "location").text = audio_path.resolve().as_uri()
+        ElementTree.SubElement(track, "title").text = info.title
+        ElementTree.SubElement(track, "creator").text = info.artist
+        entry_count += 1
+    ElementTree.indent(playlist)
+    ElementTree.ElementTree(playlist).write(output_fpl, encoding="utf-8", xml_declaration=True)
+    return entry_count
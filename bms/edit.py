@@ -0,0 +1,883 @@
+"""
+BMS文件按字节逐行编辑，避免重新编码导致Shift-JIS等编码的内容损坏。
+"""
+
+import math
+import re
+import shutil
+import unicodedata
+from dataclasses import dataclass
+from enum import Enum
+from fractions import Fraction
+from pathlib import Path
+
+from bms import AUDIO_FILE_EXTS, BMS_FILE_EXTS, IMAGE_FILE_EXTS, VIDEO_FILE_EXTS, get_dir_encoding
+from bms.check import MediaFileLookup, count_bms_notes
+from bms.encoding import get_bms_file_str
+from bms.parse import (
+    BMSDifficulty,
+    BMSInfo,
+    match_bms_header,
+    parse_bms_file,
+    parse_bms_slot_definitions,
+    read_bms_file_str,
+)
+from bms.work import extract_work_name
+from fs import walk_bms_tree
+from fs.name import get_valid_fs_name, strip_difficulty_suffix_from_dir_name
+
+
+def read_bms_file_lines(bms_file: Path) -> list[bytes]:
+    """读取BMS文件的所有行，保留行尾"""
+    return bms_file.read_bytes().splitlines(keepends=True)
+
+
+def write_bms_file_lines(bms_file: Path, lines: list[bytes]) -> None:
+    bms_file.write_bytes(b"".join(lines))
+
+
+def split_line_ending(line: bytes) -> tuple[bytes, bytes]:
+    """将行拆分为 (内容, 行尾)"""
+    body = line.rstrip(b"\r\n")
+    return body, line[len(body) :]
+
+
+def get_dir_bms_file_paths(work_dir: Path) -> list[Path]:
+    """作品目录第一层中的BMS文件（不含BMSON）"""
+    return sorted(p for p in work_dir.iterdir() if p.is_file() and p.name.lower().endswith(BMS_FILE_EXTS))
+
+
+RE_BMP_HEADER = re.compile(rb"^(\s*#BMP[0-9A-Za-z]{2}\s+)(.*?)(\s*)$", re.IGNORECASE)
+LR2_MOVIE_DIR_PREFIXES = (b"movie\\", b"movie/")
+
+
+def _find_child_dir_ignore_case(parent_dir: Path, name: str) -> Path | None:
+    for child in parent_dir.iterdir():
+        if child.is_dir() and child.name.lower() == name.lower():
+            return child
+    return None
+
+
+def migrate_lr2_to_beatoraja_skin_dir(work_dir: Path) -> int:
+    """
+    LR2可以读取 "Movie\\bga.mpg" 这样以反斜杠分隔的BGA路径，beatoraja则不一定能找到该文件。
+    将#BMP中引用的Movie子目录内的文件移动到作品目录下，并将引用改为文件名。
+
+    :return: 修改的#BMP行数
+    """
+    encoding = get_dir_encoding(work_dir)
+    adjust_count = 0
+    for bms_file in get_dir_bms_file_paths(work_dir):
+        lines = read_bms_file_lines(bms_file)
+        changed = False
+        for i, line in enumerate(lines):
+            body, ending = split_line_ending(line)
+            match = RE_BMP_HEADER.match(body)
+            if match is None:
+                continue
+            prefix, value, suffix = match.groups()
+            if not value.lower().startswith(LR2_MOVIE_DIR_PREFIXES):
+                continue
+            movie_dir = _find_child_dir_ignore_case(work_dir, "movie")
+            if movie_dir is None:
+                continue
+            new_value = value[len(LR2_MOVIE_DIR_PREFIXES[0]) :]
+            file_name = get_bms_file_str(new_value, encoding)
+            src_path = movie_dir / file_name
+            dst_path = work_dir / file_name
+            if src_path.is_file() and not dst_path.exists():
+                print(f" - Moving {src_path} to {dst_path}")
+                shutil.move(src_path, dst_path)
+            if not dst_path.is_file():
+                print(f" !_! {bms_file.name}: BGA file not found: {file_name}")
+                continue
+            lines[i] = prefix + new_value + suffix + ending
+            changed = True
+            adjust_count += 1
+        if changed:
+            write_bms_file_lines(bms_file, lines)
+
+    # Remove empty Movie dir
+    movie_dir = _find_child_dir_ignore_case(work_dir, "movie")
+    if movie_dir is not None and not any(movie_dir.iterdir()):
+        movie_dir.rmdir()
+    return adjust_count
+
+
+RE_HEADER_KEY = re.compile(rb"^(\s*#)([A-Za-z]+)")
+
+
+def canonicalise_bms_header_keys(bms_file: Path) -> int:
+    """
+    将头部命令的字母部分转为大写，如 "#title" -> "#TITLE"，值保持不变。
+
+    :return: 修改的行数
+    """
+    lines = read_bms_file_lines(bms_file)
+    changed_count = 0
+    for i, line in enumerate(lines):
+        match = RE_HEADER_KEY.match(line)
+        if match is None:
+            continue
+        key = match.group(2)
+        if key.isupper():
+            continue
+        lines[i] = match.group(1) + key.upper() + line[match.end() :]
+        changed_count += 1
+    if changed_count > 0:
+        write_bms_file_lines(bms_file, lines)
+    return changed_count
+
+
+BMS_HEADER_ORDER = (
+    b"PLAYER",
+    b"GENRE",
+    b"TITLE",
+    b"ARTIST",
+    b"BPM",
+    b"PLAYLEVEL",
+    b"RANK",
+    b"TOTAL",
+    b"STAGEFILE",
+    b"BANNER",
+    None,  # 其他头部命令
+    b"BMP",
+    b"WAV",
+)
+
+# 带两位36进制编号的头部命令，如 "#WAVA1"、"#BMPZZ"，RE_HEADER_KEY会将编号中的字母也算入命令名
+RE_SLOT_HEADER_KEY = re.compile(rb"^\s*#(BMP|WAV|BPM|STOP)[0-9A-Za-z]{2}(?:\s|$)", re.IGNORECASE)
+
+BMS_CONTROL_FLOW_KEYS = (
+    b"RANDOM",
+    b"SETRANDOM",
+    b"ENDRANDOM",
+    b"IF",
+    b"ELSEIF",
+    b"ELSE",
+    b"ENDIF",
+    b"SWITCH",
+    b"SETSWITCH",
+    b"CASE",
+    b"DEF",
+    b"SKIP",
+    b"ENDSW",
+)
+# 开始与结束一个控制流块的命令
+BMS_BLOCK_BEGIN_KEYS = (b"RANDOM", b"SETRANDOM", b"IF", b"SWITCH", b"SETSWITCH")
+BMS_BLOCK_END_KEYS = (b"ENDRANDOM", b"ENDIF", b"ENDSW")
+
+
+def _get_header_order_rank(key: bytes) -> int:
+    key = key.upper()
+    if key in BMS_HEADER_ORDER:
+        return BMS_HEADER_ORDER.index(key)
+    return BMS_HEADER_ORDER.index(None)
+
+
+def sort_bms_header_lines(bms_file: Path) -> None:
+    """
+    将头部区域（第一行 "#xxxCC:data" 之前）的头部命令按规范顺序重排，同类命令保持原有顺序。
+    空行与注释行留在原位；数据行不变。头部区域含有#RANDOM等控制流时不做修改。
+    """
+    lines = read_bms_file_lines(bms_file)
+    header_end = next((i for i, line in enumerate(lines) if RE_CHANNEL_DATA_LINE.match(line)), len(lines))
+    commands: list[tuple[int, int]] = []  # (行号, 顺序)
+    for i in range(header_end):
+        match = RE_HEADER_KEY.match(lines[i])
+        if match is None:
+            continue
+        if match.group(2).upper() in BMS_CONTROL_FLOW_KEYS:
+            return
+        slot_match = RE_SLOT_HEADER_KEY.match(lines[i])
+        key = slot_match.group(1) if slot_match is not None else match.group(2)
+        commands.append((i, _get_header_order_rank(key)))
+    command_indexes = [i for i, _ in commands]
+    sorted_lines = [lines[i] for i, _ in sorted(commands, key=lambda command: command[1])]
+    if sorted_lines == [lines[i] for i in command_indexes]:
+        return
+    for i, line in zip(command_indexes, sorted_lines, strict=True):
+        lines[i] = line
+    write_bms_file_lines(bms_file, lines)
+
+
+class KeepStrategy(Enum):
+    First = 0
+    Last = 1
+
+
+def repair_bms_duplicate_header_lines(bms_file: Path, keep: KeepStrategy) -> int:
+    """
+    对出现多次的头部命令（如两行#TITLE、两行#WAV01），只保留第一次或最后一次出现的行。
+    #RANDOM/#IF/#SWITCH 块内的行与控制流命令本身不参与处理。
+
+    :return: 移除的行数
+    """
+    lines = read_bms_file_lines(bms_file)
+    key_line_indexes: dict[bytes, list[int]] = {}
+    branch_depth = 0
+    for i, line in enumerate(lines):
+        match = RE_HEADER_KEY.match(line)
+        if match is None or RE_CHANNEL_DATA_LINE.match(line):
+            continue
+        command = match.group(2).upper()
+        if command in BMS_BLOCK_BEGIN_KEYS:
+            branch_depth += 1
+        elif command in BMS_BLOCK_END_KEYS:
+            branch_depth = max(branch_depth - 1, 0)
+        if branch_depth > 0 or command in BMS_CONTROL_FLOW_KEYS:
+            continue
+        key = split_line_ending(line)[0].split(maxsplit=1)[0].upper()
+        key_line_indexes.setdefault(key, []).append(i)
+
+    removed_lines: set[int] = set()
+    for indexes in key_line_indexes.values():
+        kept_index = indexes[0] if keep == KeepStrategy.First else indexes[-1]
+        removed_lines.update(i for i in indexes if i != kept_index)
+    if len(removed_lines) > 0:
+        write_bms_file_lines(bms_file, [line for i, line in enumerate(lines) if i not in removed_lines])
+    return len(removed_lines)
+
+
+RE_FILE_REF_HEADER = re.compile(
+    rb"^(\s*#(?:WAV[0-9A-Za-z]{2}|BMP[0-9A-Za-z]{2}|STAGEFILE|BANNER|BACKBMP)\s+)(.*?)(\s*)$", re.IGNORECASE
+)
+
+
+def replace_bms_file_reference_ext(work_dir: Path, file_name: str, new_ext: str) -> int:
+    """
+    将作品目录中各BMS文件对 file_name 的引用（#WAV、#BMP、#STAGEFILE等）改为新扩展名，如 ".bmp" -> ".png"。
+    仅替换扩展名部分，文件名本身的字节保持不变。
+
+    :return: 修改的行数
+    """
+    return replace_bms_file_references_ext(work_dir, [file_name], new_ext)
+
+
+def replace_bms_file_references_ext(work_dir: Path, file_names: list[str], new_ext: str) -> int:
+    """
+    与 replace_bms_file_reference_ext 相同，但一次处理多个文件名，每个BMS文件只读写一次。
+    子文件夹中的文件以 "/" 或 "\\" 分隔均可匹配。
+
+    :return: 修改的行数
+    """
+    encoding = get_dir_encoding(work_dir)
+    # 规范化的引用名 -> 旧扩展名的长度
+    old_ext_lens = {name.replace("\\", "/").lower(): len(Path(name).suffix) for name in file_names}
+    changed_count = 0
+    for bms_file in get_dir_bms_file_paths(work_dir):
+        lines = read_bms_file_lines(bms_file)
+        changed = False
+        for i, line in enumerate(lines):
+            body, ending = split_line_ending(line)
+            match = RE_FILE_REF_HEADER.match(body)
+            if match is None:
+                continue
+            prefix, value, suffix = match.groups()
+            old_ext_len = old_ext_lens.get(get_bms_file_str(value, encoding).replace("\\", "/").lower())
+            if old_ext_len is None:
+                continue
+            lines[i] = prefix + value[: len(value) - old_ext_len] + new_ext.encode("ascii") + suffix + ending
+            changed = True
+            changed_count += 1
+        if changed:
+            write_bms_file_lines(bms_file, lines)
+    return changed_count
+
+
+def set_bms_header_value(bms_file: Path, key: str, value: bytes) -> None:
+    """
+    设置头部命令的值，如 set_bms_header_value(file, "STAGEFILE", b"stage.png")。
+    已存在时替换第一处，否则插入到#TITLE之后（无#TITLE时插入到文件开头）。
+    """
+    pattern = re.compile(rb"^(\s*#" + re.escape(key.encode("ascii")) + rb")(?:\s|$)", re.IGNORECASE)
+    lines = read_bms_file_lines(bms_file)
+    default_ending = split_line_ending(lines[0])[1] if len(lines) > 0 else b""
+    new_body = b"#" + key.upper().encode("ascii") + b" " + value
+    insert_index = 0
+    for i, line in enumerate(lines):
+        body, ending = split_line_ending(line)
+        if pattern.match(body) is not None:
+            lines[i] = new_body + ending
+            write_bms_file_lines(bms_file, lines)
+            return
+        if insert_index == 0 and re.match(rb"^\s*#TITLE(?:\s|$)", body, re.IGNORECASE) is not None:
+            insert_index = i + 1
+    if insert_index > 0 and split_line_ending(lines[insert_index - 1])[1] == b"":
+        # #TITLE位于最后一行且没有行尾
+        lines[insert_index - 1] += default_ending or b"\r\n"
+    lines.insert(insert_index, new_body + (default_ending or b"\r\n"))
+    write_bms_file_lines(bms_file, lines)
+
+
+UTF8_BOM = b"\xef\xbb\xbf"
+
+
+def check_bms_file_utf8_bom(bms_file: Path) -> bool:
+    """BMS文件是否以UTF-8 BOM（EF BB BF）开头"""
+    with bms_file.open("rb") as f:
+        return f.read(len(UTF8_BOM)) == UTF8_BOM
+
+
+def strip_bms_file_utf8_bom(bms_file: Path) -> bool:
+    """
+    移除BMS文件开头的UTF-8 BOM。
+
+    :return: 是否进行了修改
+    """
+    if not check_bms_file_utf8_bom(bms_file):
+        return False
+    bms_file.write_bytes(bms_file.read_bytes()[len(UTF8_BOM) :])
+    return True
+
+
+RE_CHANNEL_DATA_LINE = re.compile(rb"^(\s*#\d{3,})([0-9A-Za-z]{2})(:)(.*?)(\s*)$")
+# 可见物件通道（1P: 1x, 2P: 2x）与长条通道（1P: 5x, 2P: 6x）
+NOTE_CHANNEL_PREFIXES = (b"1", b"2", b"5", b"6")
+
+
+def _align_channel_data(data: bytes, grid: int) -> tuple[bytes, int]:
+    """将一行数据中的物件对齐到 1/grid 网格，返回 (新数据, 移动的物件数)"""
+    data = b"".join(data.split())
+    objects = [data[i : i + 2] for i in range(0, len(data) - 1, 2)]
+    aligned: dict[int, bytes] = {}
+    moved_count = 0
+    for i, obj in enumerate(objects):
+        if obj == b"00":
+            continue
+        position = Fraction(i, len(objects))
+        # 小节末尾的物件不会被移动到下一小节
+        grid_index = min(round(position * grid), grid - 1)
+        if Fraction(grid_index, grid) != position:
+            moved_count += 1
+        if grid_index in aligned:
+            print(f" !_! Note {obj.decode()} collides with {aligned[grid_index].decode()} after aligning, dropped")
+            continue
+        aligned[grid_index] = obj
+    if moved_count == 0:
+        return data, 0
+    # 使用能容纳所有物件的最小分母
+    step = math.gcd(grid, *aligned.keys())
+    new_objects = [b"00"] * (grid // step)
+    for grid_index, obj in aligned.items():
+        new_objects[grid_index // step] = obj
+    return b"".join(new_objects), moved_count
+
+
+def align_bms_notes_to_grid(bms_file: Path, grid: int, dry_run: bool) -> int:
+    """
+    将物件通道中的所有物件对齐到最近的 1/grid 网格（如grid=48即1/48小节）。
+    对齐后位于同一位置的多个物件只保留第一个。
+
+    :return: 移动的物件数（dry_run时不写入文件）
+    """
+    lines = read_bms_file_lines(bms_file)
+    moved_count = 0
+    for i, line in enumerate(lines):
+        body, ending = split_line_ending(line)
+        match = RE_CHANNEL_DATA_LINE.match(body)
+        if match is None or not match.group(2).startswith(NOTE_CHANNEL_PREFIXES):
+            continue
+        prefix, channel, colon, data, suffix = match.groups()
+        new_data, line_moved_count = _align_channel_data(data, grid)
+        if line_moved_count == 0:
+            continue
+        lines[i] = prefix + channel + colon + new_data + suffix + ending
+        moved_count += line_moved_count
+    if moved_count > 0 and not dry_run:
+        write_bms_file_lines(bms_file, lines)
+    return moved_count
+
+
+STRIPPED_COMMENT_MARKER = b"*STRIPPED BY bms-resource-scripts"
+
+
+def strip_comment_lines_from_bms(bms_file: Path, dry_run: bool) -> int:
+    """
+    移除以 "*" 或 "//" 开头的注释行，并在文件开头保留一行 "*STRIPPED BY bms-resource-scripts" 作为记录。
+
+    :return: 移除的行数（dry_run时不写入文件）
+    """
+    lines = read_bms_file_lines(bms_file)
+    kept_lines: list[bytes] = []
+    removed_count = 0
+    for line in lines:
+        body = split_line_ending(line)[0].lstrip()
+        if body == STRIPPED_COMMENT_MARKER:
+            continue
+        if body.startswith((b"*", b"//")):
+            removed_count += 1
+            continue
+        kept_lines.append(line)
+    if removed_count > 0 and not dry_run:
+        ending = split_line_ending(lines[0])[1] or b"\r\n"
+        write_bms_file_lines(bms_file, [STRIPPED_COMMENT_MARKER + ending, *kept_lines])
+    return removed_count
+
+
+def _get_blank_line_runs(lines: list[bytes]) -> list[tuple[int, int]]:
+    """连续空行的 (起始行号, 行数) 列表"""
+    runs: list[tuple[int, int]] = []
+    run_start = -1
+    for i, line in enumerate([*lines, b"END"]):
+        if split_line_ending(line)[0].strip() == b"":
+            if run_start < 0:
+                run_start = i
+        elif run_start >= 0:
+            runs.append((run_start, i - run_start))
+            run_start = -1
+    return runs
+
+
+def detect_bms_excessive_blank_lines(bms_file: Path, threshold: int) -> int | None:
+    """
+    :return: 最长连续空行数超过 threshold 时返回该行数，否则返回None
+    """
+    max_run = max((count for _, count in _get_blank_line_runs(read_bms_file_lines(bms_file))), default=0)
+    return max_run if max_run > threshold else None
+
+
+def strip_excessive_blank_lines(bms_file: Path, max_run: int) -> int:
+    """
+    将超过 max_run 行的连续空行缩减为 max_run 行。
+
+    :return: 移除的行数
+    """
+    lines = read_bms_file_lines(bms_file)
+    removed_lines: set[int] = set()
+    for start, count in _get_blank_line_runs(lines):
+        removed_lines.update(range(start + max_run, start + count))
+    if len(removed_lines) > 0:
+        write_bms_file_lines(bms_file, [line for i, line in enumerate(lines) if i not in removed_lines])
+    return len(removed_lines)
+
+
+RE_HEADER_KEY_SPACING = re.compile(rb"^(\s*#[A-Za-z0-9]+)[ \t]+")
+
+
+def normalize_bms_whitespace(bms_file: Path) -> int:
+    """
+    统一空白：头部命令与值之间的多个空白合并为一个空格，去除行尾空白，行尾统一为LF。
+    值内部的空白保持不变。
+
+    :return: 修改的行数
+    """
+    lines = read_bms_file_lines(bms_file)
+    changed_count = 0
+    for i, line in enumerate(lines):
+        body, ending = split_line_ending(line)
+        new_body = body.rstrip()
+        if RE_HEADER_KEY.match(new_body) and not RE_CHANNEL_DATA_LINE.match(new_body):
+            new_body = RE_HEADER_KEY_SPACING.sub(rb"\1 ", new_body, count=1)
+        new_line = new_body + (b"\n" if len(ending) > 0 else b"")
+        if new_line != line:
+            lines[i] = new_line
+            changed_count += 1
+    if changed_count > 0:
+        write_bms_file_lines(bms_file, lines)
+    return changed_count
+
+
+# 不使用.bml扩展名，否则会被视为谱面文件
+BMS_INDEX_LIST_FILE_NAME = "index.lst"
+
+
+def generate_bms_index_list(work_dir: Path) -> Path:
+    """
+    在作品目录中生成 index.lst，以 "#BMS {文件名}" 的形式逐行列出所有 .bms/.bme/.pms 文件（不含已有的.bml）。
+
+    :return: 生成的文件路径
+    """
+    file_names = [p.name for p in get_dir_bms_file_paths(work_dir) if p.suffix.lower() != ".bml"]
+    file_str = "".join(f"#BMS {file_name}\r\n" for file_name in file_names)
+    try:
+        file_bytes = file_str.encode(get_dir_encoding(work_dir) or "shift_jis")
+    except UnicodeEncodeError:
+        file_bytes = file_str.encode("utf-8")
+    index_path = work_dir / BMS_INDEX_LIST_FILE_NAME
+    index_path.write_bytes(file_bytes)
+    return index_path
+
+
+@dataclass
+class RecoveryReport:
+    # 截断后的文件大小；文件完整时为None
+    truncated_at_byte: int | None
+    # 保留的行数
+    recovered_lines: int
+    # 估计丢失的行数（下限）；文件完整时为None
+    lost_lines_estimate: int | None
+
+
+# 没有值的头部命令
+BMS_VALUELESS_COMMANDS = (b"#ENDIF", b"#ENDRANDOM", b"#ENDSW", b"#SKIP", b"#DEF", b"#ELSE")
+
+
+def _is_partial_bms_line(body: bytes) -> bool:
+    """判断没有行尾的最后一行是否为不完整的命令"""
+    body = body.strip()
+    if not body.startswith(b"#"):
+        return False
+    if body[1:4].isdigit():
+        match = RE_CHANNEL_DATA_LINE.match(body)
+        return match is None or len(b"".join(match.group(4).split())) % 2 == 1
+    return len(body.split(maxsplit=1)) < 2 and body.upper() not in BMS_VALUELESS_COMMANDS
+
+
+def recover_truncated_bms_file(bms_file: Path) -> RecoveryReport:
+    """
+    检查BMS文件是否在传输中被截断（末尾填充了NUL字节，或最后一行为不完整的命令且没有行尾），
+    若是，则移除不完整的部分并写回文件。
+    """
+    file_bytes = bms_file.read_bytes()
+    recovered_bytes = file_bytes.rstrip(b"\0")
+    lines = recovered_bytes.splitlines(keepends=True)
+    lost_lines = 0
+    if len(lines) > 0:
+        body, ending = split_line_ending(lines[-1])
+        if ending == b"" and _is_partial_bms_line(body):
+            lines.pop()
+            lost_lines = 1
+            recovered_bytes = b"".join(lines)
+    if len(recovered_bytes) == len(file_bytes):
+        return RecoveryReport(None, len(lines), None)
+    bms_file.write_bytes(recovered_bytes)
+    return RecoveryReport(len(recovered_bytes), len(lines), lost_lines)
+
+
+@dataclass
+class LinearTotalFormula:
+    base: float
+    per_note: float
+
+    def compute(self, notes: int) -> float:
+        return self.base + self.per_note * notes
+
+
+@dataclass
+class Lr2CompatibleTotalFormula:
+    """未指定#TOTAL时LR2（及beatoraja）使用的默认值"""
+
+    def compute(self, notes: int) -> float:
+        return 160.0 + (notes + min(max(notes - 400, 0), 200)) * 0.16
+
+
+TotalFormula = LinearTotalFormula | Lr2CompatibleTotalFormula
+
+
+def _format_total(total: float) -> str:
+    return f"{total:.2f}".rstrip("0").rstrip(".")
+
+
+def batch_update_bms_total_field(root_dir: Path, formula: TotalFormula) -> int:
+    """
+    根据物件数重新计算根目录下（递归）所有BMS文件的#TOTAL，仅写回数值有变化的文件。
+
+    :return: 修改的文件数
+    """
+    changed_count = 0
+    for bms_file in sorted(walk_bms_tree(root_dir)):
+        if not bms_file.is_file() or not bms_file.name.lower().endswith(BMS_FILE_EXTS):
+            continue
+        file_str = read_bms_file_str(bms_file)
+        old_total: str | None = None
+        for line in file_str.splitlines():
+            old_total = match_bms_header(line, "TOTAL") or old_total
+        new_total = _format_total(formula.compute(count_bms_notes(file_str)))
+        try:
+            if old_total is not None and float(old_total) == float(new_total):
+                continue
+        except ValueError:
+            pass
+        print(f" - {bms_file}: #TOTAL {old_total} -> {new_total}")
+        set_bms_header_value(bms_file, "TOTAL", new_total.encode("ascii"))
+        changed_count += 1
+    return changed_count
+
+
+def batch_rename_bms_files_to_chart_name(work_dir: Path, dry_run: bool) -> list[tuple[Path, Path]]:
+    """
+    按#DIFFICULTY和#PLAYLEVEL重命名作品目录中的BMS文件，如 "a.bms" -> "another_12.bms"，扩展名保持不变。
+    名称冲突时追加序号，如 "another_12_2.bms"。#DIFFICULTY缺失或无效、#PLAYLEVEL超出范围的文件保持原名。
+
+    :return: (原路径, 新路径) 列表；dry_run时仅返回，不重命名
+    """
+    encoding = get_dir_encoding(work_dir)
+    bms_infos: list[tuple[Path, BMSInfo]] = []
+    for bms_file in get_dir_bms_file_paths(work_dir):
+        info = parse_bms_file(bms_file, encoding)
+        if info.difficulty is BMSDifficulty.Unknown or info.playlevel < 0:
+            print(f" !_! {bms_file.name}: Unknown #DIFFICULTY or #PLAYLEVEL, skipping.")
+            continue
+        bms_infos.append((bms_file, info))
+    renaming_names = {bms_file.name.lower() for bms_file, _ in bms_infos}
+    used_names = {p.name.lower() for p in work_dir.iterdir() if p.name.lower() not in renaming_names}
+    pairs: list[tuple[Path, Path]] = []
+    for bms_file, info in bms_infos:
+        base_name = f"{info.difficulty.name.lower()}_{info.playlevel}"
+        new_name = f"{base_name}{bms_file.suffix}"
+        counter = 1
+        while new_name.lower() in used_names:
+            counter += 1
+            new_name = f"{base_name}_{counter}{bms_file.suffix}"
+        used_names.add(new_name.lower())
+        if new_name != bms_file.name:
+            pairs.append((bms_file, work_dir / new_name))
+    if dry_run:
+        return pairs
+
+    # 先改为临时名称，避免 a -> b、b -> c 这样的链式重命名互相覆盖
+    tmp_paths = [bms_file.rename(bms_file.with_name(f"{bms_file.name}.renaming")) for bms_file, _ in pairs]
+    for tmp_path, (_bms_file, new_path) in zip(tmp_paths, pairs, strict=True):
+        tmp_path.rename(new_path)
+    return pairs
+
+
+# 作品名之后的难度标签等后缀的起始字符，如 "Song [SPA]"、"Song (SP ANOTHER)"、"Song -Another-"
+TITLE_SUFFIX_OPENINGS = ("(", "[", "{", "（", "［", "｛", "【", "<", "＜", "-", "~", "～", "〜")
+
+
+def _get_common_work_name(title_a: str, title_b: str) -> str | None:
+    """两个标题仅在难度标签等后缀上不同时，返回共同的作品名；否则返回None"""
+    work_name = extract_work_name([title_a, title_b], remove_tailing_sign_list=["-", "~", "～", "〜"])
+    # 没有共同前缀时，extract_work_name会返回其中一个完整的标题
+    if len(work_name) == 0 or not title_a.startswith(work_name) or not title_b.startswith(work_name):
+        return None
+    for title in (title_a, title_b):
+        suffix = title[len(work_name) :].strip()
+        if len(suffix) > 0 and not suffix.startswith(TITLE_SUFFIX_OPENINGS):
+            return None
+    return work_name
+
+
+def _group_bms_files_by_work_name(work_dir: Path) -> dict[str, list[Path]]:
+    """将作品目录中的BMS文件按#TITLE中的作品名分组（与get_dir_bms_info相同，使用extract_work_name提取作品名）"""
+    encoding = get_dir_encoding(work_dir)
+    groups: dict[str, list[Path]] = {}
+    for bms_file in get_dir_bms_file_paths(work_dir):
+        title = unicodedata.normalize("NFKC", parse_bms_file(bms_file, encoding).title).strip()
+        group_name = title
+        if title not in groups:
+            for name in groups:
+                work_name = _get_common_work_name(name, title)
+                if work_name is None:
+                    continue
+                # 合并后的作品名可能更短，如 "Song [SPN]" + "Song [SPH]" -> "Song"
+                group_files = groups.pop(name)
+                groups.setdefault(work_name, []).extend(group_files)
+                group_name = work_name
+                break
+        groups.setdefault(group_name, []).append(bms_file)
+    return groups
+
+
+@dataclass
+class TitleGroupPlan:
+    group_dir: Path
+    bms_files: list[Path]
+    # 仅被该组引用的媒体文件，移入group_dir
+    media_files: list[Path]
+    # 被多组共用的媒体文件，复制到group_dir
+    shared_files: list[Path]
+
+
+def plan_bms_title_groups(work_dir: Path) -> list[TitleGroupPlan]:
+    """计算 group_related_bms_files_by_title 的分组方式，不修改文件系统"""
+    encoding = get_dir_encoding(work_dir)
+    lookup = MediaFileLookup(work_dir)
+    groups = _group_bms_files_by_work_name(work_dir)
+
+    # 媒体文件 -> 引用它的分组
+    file_groups: dict[Path, set[str]] = {}
+    for key, bms_files in groups.items():
+        for bms_file in bms_files:
+            file_str = read_bms_file_str(bms_file, encoding)
+            for command in ("WAV", "BMP"):
+                for name in parse_bms_slot_definitions(file_str, command).values():
+                    media_path = lookup.get_audio_path(name) if command == "WAV" else lookup.get_path(name)
+                    if media_path is not None:
+                        file_groups.setdefault(media_path, set()).add(key)
+
+    return [
+        TitleGroupPlan(
+            group_dir=work_dir / (get_valid_fs_name(strip_difficulty_suffix_from_dir_name(key)) or "untitled"),
+            bms_files=bms_files,
+            media_files=sorted(path for path, keys in file_groups.items() if keys == {key}),
+            shared_files=sorted(path for path, keys in file_groups.items() if len(keys) > 1 and key in keys),
+        )
+        for key, bms_files in groups.items()
+    ]
+
+
+def group_related_bms_files_by_title(work_dir: Path, dry_run: bool, group_single: bool = False) -> int:
+    """
+    作品目录中混有多首歌曲时，按#TITLE中的作品名（忽略难度标签等后缀）分组，为每组创建子目录，
+    并将BMS文件及仅被该组引用的#WAV/#BMP文件移入其中。被多组共用的文件复制到各组的子目录中，以保证各谱面的引用仍然有效。
+    group_single为True时，只有一组也会创建子目录（用于整理散落在大包根目录中的谱面）。
+
+    :return: 创建的分组数；只有一组且group_single为False时不做处理，返回0
+    """
+    plans = plan_bms_title_groups(work_dir)
+    if len(plans) == 0 or (len(plans) == 1 and not group_single):
+        return 0
+
+    shared_files: set[Path] = set()
+    for plan in plans:
+        print(
+            f" - {plan.group_dir.name}: {len(plan.bms_files)} chart(s), {len(plan.media_files)} media file(s), "
+            f"{len(plan.shared_files)} shared media file(s)"
+        )
+        if dry_run:
+            continue
+        plan.group_dir.mkdir(exist_ok=True)
+        for file_path in plan.shared_files:
+            target_path = plan.group_dir / file_path.relative_to(work_dir)
+            target_path.parent.mkdir(parents=True, exist_ok=True)
+            shutil.copy2(file_path, target_path)
+        for file_path in plan.bms_files + plan.media_files:
+            target_path = plan.group_dir / file_path.relative_to(work_dir)
+            target_path.parent.mkdir(parents=True, exist_ok=True)
+            shutil.move(file_path, target_path)
+        shared_files.update(plan.shared_files)
+    # 共用的文件已复制到各组中
+    for file_path in shared_files:
+        file_path.unlink()
+    return len(plans)
+
+
+RE_WAV_BMP_HEADER = re.compile(rb"^(\s*#(WAV|BMP)[0-9A-Za-z]{2}\s+)(.*?)(\s*)$", re.IGNORECASE)
+
+
+def split_work_dir_media_by_channel(work_dir: Path, sound_dir: str, movie_dir: str) -> int:
+    """
+    将#WAV引用的音频文件移动到 {sound_dir}/，#BMP引用的视频和图片移动到 {movie_dir}/，
+    并将BMS文件中对应的引用改为 "{sound_dir}/文件名"、"{movie_dir}/文件名"。目标已存在同名文件时不移动。
+
+    :return: 移动的文件数
+    """
+    encoding = get_dir_encoding(work_dir)
+    lookup = MediaFileLookup(work_dir)
+    bms_files = get_dir_bms_file_paths(work_dir)
+    dir_names = {b"WAV": sound_dir, b"BMP": movie_dir}
+    target_exts = {b"WAV": AUDIO_FILE_EXTS, b"BMP": VIDEO_FILE_EXTS + IMAGE_FILE_EXTS}
+
+    moves: dict[Path, Path] = {}
+    # (BMS文件, 行号) -> 引用的文件
+    line_refs: dict[tuple[Path, int], Path] = {}
+    bms_file_lines = {bms_file: read_bms_file_lines(bms_file) for bms_file in bms_files}
+    for bms_file, lines in bms_file_lines.items():
+        for i, line in enumerate(lines):
+            match = RE_WAV_BMP_HEADER.match(split_line_ending(line)[0])
+            if match is None:
+                continue
+            command = match.group(2).upper()
+            name = get_bms_file_str(match.group(3), encoding)
+            file_path = lookup.get_audio_path(name) if command == b"WAV" else lookup.get_path(name)
+            if file_path is None or file_path.suffix.lower() not in target_exts[command]:
+                continue
+            moves[file_path] = work_dir / dir_names[command] / file_path.name
+            line_refs[(bms_file, i)] = file_path
+
+    moved_count = 0
+    skipped_paths: set[Path] = set()
+    for file_path, target_path in moves.items():
+        if file_path == target_path:
+            continue
+        if target_path.exists():
+            print(f" !_! {target_path} already exists, skipping.")
+            skipped_paths.add(file_path)
+            continue
+        target_path.parent.mkdir(exist_ok=True)
+        shutil.move(file_path, target_path)
+        moved_count += 1
+
+    for bms_file, lines in bms_file_lines.items():
+        changed = False
+        for i, line in enumerate(lines):
+            file_path = line_refs.get((bms_file, i))
+            if file_path is None or file_path in skipped_paths:
+                continue
+            body, ending = split_line_ending(line)
+            match = RE_WAV_BMP_HEADER.match(body)
+            if match is None:
+                continue
+            prefix, command, value, suffix = match.groups()
+            base_name = re.split(rb"[\\/]", value)[-1]
+            new_value = dir_names[command.upper()].encode(encoding or "shift_jis") + b"/" + base_name
+            if new_value != value:
+                lines[i] = prefix + new_value + suffix + ending
+                changed = True
+        if changed:
+            write_bms_file_lines(bms_file, lines)
+    return moved_count
+
+
+class CaseFixStrategy(Enum):
+    # 将磁盘上的文件改名为BMS中声明的大小写
+    RenameFileToBms = 0
+    # 将BMS中的#WAV声明改为磁盘上文件的大小写
+    PatchBmsToFile = 1
+
+
+def repair_bms_wav_case_mismatch(work_dir: Path, strategy: CaseFixStrategy) -> int:
+    """
+    修复#WAV声明与磁盘上文件名仅大小写不同的问题（大小写敏感的文件系统上会找不到文件）。
+    RenameFileToBms时，若多个BMS文件对同一文件的写法互相冲突，或目录部分的大小写也不同，
+    则无法通过改名满足所有声明，改为修改其中不一致的#WAV声明。
+
+    :return: 改名的文件数与修改的行数之和
+    """
+    encoding = get_dir_encoding(work_dir)
+    lookup = MediaFileLookup(work_dir)
+    bms_file_lines = {bms_file: read_bms_file_lines(bms_file) for bms_file in get_dir_bms_file_paths(work_dir)}
+
+    # 文件 -> [(BMS文件, 行号, 声明的相对路径)]
+    file_refs: dict[Path, list[tuple[Path, int, str]]] = {}
+    for bms_file, lines in bms_file_lines.items():
+        for i, line in enumerate(lines):
+            match = RE_WAV_BMP_HEADER.match(split_line_ending(line)[0])
+            if match is None or match.group(2).upper() != b"WAV":
+                continue
+            name = get_bms_file_str(match.group(3), encoding).strip().replace("\\", "/")
+            file_path = lookup.get_path(name)
+            if file_path is not None:
+                file_refs.setdefault(file_path, []).append((bms_file, i, name))
+
+    fixed_count = 0
+    patch_refs: list[tuple[Path, int, str]] = []
+    for file_path, refs in file_refs.items():
+        actual_name = file_path.relative_to(work_dir).as_posix()
+        names = {name for _bms_file, _i, name in refs}
+        if names == {actual_name}:
+            continue
+        if strategy == CaseFixStrategy.RenameFileToBms and len(names) == 1:
+            new_name = names.pop()
+            if Path(new_name).parent.as_posix() == Path(actual_name).parent.as_posix():
+                # 经由临时名称改名，兼容大小写不敏感的文件系统
+                temp_path = file_path.with_name(file_path.name + ".__case_tmp__")
+                file_path.rename(temp_path)
+                temp_path.rename(work_dir / new_name)
+                print(f" - {actual_name} -> {new_name}")
+                fixed_count += 1
+                continue
+        patch_refs.extend((bms_file, i, actual_name) for bms_file, i, name in refs if name != actual_name)
+
+    changed_bms_files: set[Path] = set()
+    for bms_file, i, actual_name in patch_refs:
+        lines = bms_file_lines[bms_file]
+        body, ending = split_line_ending(lines[i])
+        match = RE_WAV_BMP_HEADER.match(body)
+        if match is None:
+            continue
+        prefix, _command, _value, suffix = match.groups()
+        try:
+            new_value = actual_name.encode(encoding or "shift_jis")
+        except UnicodeEncodeError:
+            print(f" !_! {bms_file.name}: cannot encode {actual_name}, skipping.")
+            continue
+        lines[i] = prefix + new_value + suffix + ending
+        changed_bms_files.add(bms_file)
+        fixed_count += 1
+    for bms_file in changed_bms_files:
+        write_bms_file_lines(bms_file, bms_file_lines[bms_file])
+    return fixed_count
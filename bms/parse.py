@@ -1,4 +1,5 @@
 import json
+import re
 from dataclasses import dataclass, field
 from enum import Enum
 from pathlib import Path
@@ -24,6 +25,119 @@ class BMSInfo:
     difficulty: BMSDifficulty = BMSDifficulty.Unknown
     playlevel: int = 0
     bmp_formats: list[str] = field(default_factory=list)
+    bpm: float = 0.0
+
+
+def read_bms_file_str(file_path: Path, encoding: str | None = None) -> str:
+    with file_path.open("rb") as file:
+        return get_bms_file_str(file.read(), encoding)
+
+
+def match_bms_header(line: str, key: str) -> str | None:
+    """
+    若该行为指定的头部命令（不区分大小写），返回其值，否则返回None。
+    例：match_bms_header("#TITLE Song", "TITLE") -> "Song"
+    """
+    line = line.strip()
+    command = f"#{key}"
+    if not line.upper().startswith(command.upper()):
+        return None
+    rest = line[len(command) :]
+    if len(rest) > 0 and not rest[0].isspace():
+        # 如 "#BPM01" 不属于 "#BPM"
+        return None
+    return rest.strip()
+
+
+# 数据行：#小节号(3位) 通道(2位):数据
+RE_BMS_CHANNEL_LINE = re.compile(r"^#(\d{3,})([0-9A-Za-z]{2}):(.*)$")
+
+
+@dataclass
+class BMSChannelLine:
+    line_number: int
+    measure: int
+    channel: str
+    data: str
+
+    def objects(self) -> list[str]:
+        """按两个字符一组拆分数据，"00" 表示该位置无物件"""
+        return [self.data[i : i + 2].upper() for i in range(0, len(self.data) - 1, 2)]
+
+
+def parse_bms_channel_lines(file_str: str) -> list[BMSChannelLine]:
+    """解析所有数据行，行号从1开始"""
+    channel_lines: list[BMSChannelLine] = []
+    for line_number, line in enumerate(file_str.splitlines(), start=1):
+        match = RE_BMS_CHANNEL_LINE.match(line.strip())
+        if match is None:
+            continue
+        channel_lines.append(
+            BMSChannelLine(
+                line_number,
+                int(match.group(1)),
+                match.group(2).upper(),
+                "".join(match.group(3).split()),
+            )
+        )
+    return channel_lines
+
+
+def parse_bms_slot_definitions(file_str: str, command: str) -> dict[str, str]:
+    """
+    解析形如 "#WAVxx file.wav" 的定义，返回 {槽位(大写): 值}。
+    同一槽位多次定义时，以最后一次为准。
+    """
+    pattern = re.compile(rf"^#{command}([0-9A-Za-z]{{2}})(?:\s+(.*))?$", re.IGNORECASE)
+    definitions: dict[str, str] = {}
+    for line in file_str.splitlines():
+        match = pattern.match(line.strip())
+        if match is None:
+            continue
+        definitions[match.group(1).upper()] = (match.group(2) or "").strip()
+    return definitions
+
+
+def detect_bms_subartist(file_path: Path, encoding: str | None = None) -> str | None:
+    """
+    获取BMS文件中的副艺术家信息。
+    按顺序检查：#SUBARTIST、#CREDIT、首个 "; Artist:" 注释行。
+    """
+    subartist: str | None = None
+    credit: str | None = None
+    comment_artist: str | None = None
+    for line in read_bms_file_str(file_path, encoding).splitlines():
+        line = line.strip()
+        if subartist is None:
+            subartist = match_bms_header(line, "SUBARTIST") or None
+        if credit is None:
+            credit = match_bms_header(line, "CREDIT") or None
+        if comment_artist is None and line.startswith(";"):
+            comment = line[1:].strip()
+            if comment.lower().startswith("artist:"):
+                comment_artist = comment[len("artist:") :].strip() or None
+    return subartist or credit or comment_artist
+
+
+def extract_bms_preview_range(file_path: Path, encoding: str | None = None) -> tuple[Path, float] | None:
+    """
+    获取beatoraja使用的#PREVIEW（预览音频，相对于作品目录的路径）及#PREVIEWPOINT（开始位置，秒）。
+    未指定#PREVIEWPOINT时开始位置为0；没有#PREVIEW时返回None。
+    """
+    preview: str | None = None
+    preview_point = 0.0
+    for line in read_bms_file_str(file_path, encoding).splitlines():
+        preview = match_bms_header(line, "PREVIEW") or preview
+        point_str = match_bms_header(line, "PREVIEWPOINT")
+        if point_str is None:
+            continue
+        try:
+            preview_point = float(point_str)
+        except ValueError:
+            pass
+    if preview is None:
+        return None
+    return Path(preview.replace("\\", "/")), preview_point
 
 
 def parse_bms_file(file_path: Path, encoding: str | None = None) -> BMSInfo:
@@ -32,6 +146,7 @@ def parse_bms_file(file_path: Path, encoding: str | None = None) -> BMSInfo:
     genre = ""
     difficulty = BMSDifficulty.Unknown
     playlevel = 0
+    bpm = 0.0
     ext_list = []
     with file_path.open("rb") as file:
         file_bytes = file.read()
@@ -60,8 +175,15 @@ def parse_bms_file(file_path: Path, encoding: str | None = None) -> BMSInfo:
                 ext = Path(value_str).suffix
                 if ext is not None:
                     ext_list.append(ext)
+            elif line.startswith(("#BPM ", "#BPM\t")):
+                # 仅处理 "#BPM"，跳过 "#BPMxx" 扩展BPM定义
+                value_str = line[len("#BPM") :].strip()
+                try:
+                    bpm = float(value_str)
+                except ValueError:
+                    pass
 
-    return BMSInfo(title, artist, genre, difficulty, playlevel, ext_list)
+    return BMSInfo(title, artist, genre, difficulty, playlevel, ext_list, bpm)
 
 
 def parse_bmson_file(file_path: Path, encoding: str | None = None) -> BMSInfo:
@@ -70,6 +192,7 @@ def parse_bmson_file(file_path: Path, encoding: str | None = None) -> BMSInfo:
     genre = ""
     difficulty = BMSDifficulty.Unknown
     playlevel = 0
+    bpm = 0.0
     with file_path.open("rb") as file:
         file_bytes = file.read()
         file_str = get_bms_file_str(file_bytes, encoding)
@@ -92,6 +215,7 @@ def parse_bmson_file(file_path: Path, encoding: str | None = None) -> BMSInfo:
         artist = dict_get(bmson_info, "info", "artist") or ""
         genre = dict_get(bmson_info, "info", "genre") or ""
         playlevel = int(dict_get(bmson_info, "info", "level") or 0)
+        bpm = float(dict_get(bmson_info, "info", "init_bpm") or 0.0)
         ext_list = []
         bga_headers = dict_get(bmson_info, "bga", "bga_header")
         if bga_headers is not None:
@@ -101,4 +225,4 @@ def parse_bmson_file(file_path: Path, encoding: str | None = None) -> BMSInfo:
                 if ext is not None:
                     ext_list.append(ext)
 
-    return BMSInfo(title, artist, genre, difficulty, playlevel, ext_list)
+    return BMSInfo(title, artist, genre, difficulty, playlevel, ext_list, bpm)
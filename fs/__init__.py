@@ -1,9 +1,62 @@
+import fnmatch
+import os
 import shutil
+from collections.abc import Iterator
+from dataclasses import dataclass, field
 from pathlib import Path
 
 from fs.move import is_dir_having_file
 
 
+@dataclass
+class WalkOptions:
+    # 最大深度，根目录的直接子项为1，None为不限
+    max_depth: int | None = None
+    # 是否进入指向文件夹的符号链接
+    follow_symlinks: bool = False
+    # 是否跳过以 "." 开头的文件和文件夹
+    skip_hidden: bool = False
+    # 名称匹配这些通配符（fnmatch，忽略大小写）的文件和文件夹将被跳过，文件夹不会被进入
+    ignore: list[str] = field(default_factory=list)
+
+
+def walk_bms_tree(root: Path, opts: WalkOptions | None = None) -> Iterator[Path]:
+    """
+    递归遍历root下的文件与文件夹（不含root本身），先产出文件夹再进入其中。
+    默认选项下与 root.rglob("*") 产出相同的路径集合。
+    """
+    if opts is None:
+        opts = WalkOptions()
+    ignore = [pattern.lower() for pattern in opts.ignore]
+    visited: set[str] = {os.path.realpath(root)}
+
+    def walk(dir_path: Path, depth: int) -> Iterator[Path]:
+        try:
+            entries = list(os.scandir(dir_path))
+        except OSError:
+            return
+        for entry in entries:
+            if opts.skip_hidden and entry.name.startswith("."):
+                continue
+            if any(fnmatch.fnmatchcase(entry.name.lower(), pattern) for pattern in ignore):
+                continue
+            path = dir_path / entry.name
+            yield path
+            if not entry.is_dir(follow_symlinks=opts.follow_symlinks):
+                continue
+            if opts.max_depth is not None and depth >= opts.max_depth:
+                continue
+            if entry.is_symlink():
+                # 避免符号链接成环
+                real_path = os.path.realpath(path)
+                if real_path in visited:
+                    continue
+                visited.add(real_path)
+            yield from walk(path, depth + 1)
+
+    yield from walk(root, 1)
+
+
 def remove_empty_folder(parent_dir: Path) -> None:
     for dir_path in parent_dir.iterdir():
         if not dir_path.is_dir():
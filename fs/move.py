@@ -1,10 +1,14 @@
 import multiprocessing
+import os
 import shutil
 import threading
+import time
+from collections.abc import Callable
 from concurrent.futures import ThreadPoolExecutor, as_completed
-from dataclasses import dataclass, field
+from dataclasses import dataclass, field, replace
 from enum import Enum
 from pathlib import Path
+from typing import Self
 
 
 def is_dir_having_file(dir_path: Path) -> bool:
@@ -40,6 +44,10 @@ class ReplaceAction(Enum):
     Skip = 0
     Replace = 1
     Rename = 2
+    # 源文件较新时替换
+    ReplaceIfNewer = 3
+    # 源文件音质较高（由ReplaceOptions.quality_comparator判断）时替换，无法判断时按ReplaceIfNewer处理
+    ReplaceIfHigherBitrate = 4
     CheckReplace = 12
 
 
@@ -47,12 +55,42 @@ class ReplaceAction(Enum):
 class ReplaceOptions:
     ext: dict[str, ReplaceAction] = field(default_factory=dict)
     default: ReplaceAction = ReplaceAction.Replace
+    # 仅当源文件比目标文件新时才处理
+    update_newer_only: bool = False
+    # 目标文件已存在且大小相同时跳过
+    skip_if_same_size: bool = False
+    # ReplaceIfHigherBitrate使用的质量比较：>0：源文件更好；<0：目标文件更好；0：相同；无法判断时返回None
+    quality_comparator: Callable[[Path, Path], int | None] | None = None
+
+    @classmethod
+    def update_pack(cls) -> Self:
+        """更新大包：谱面等文本文件内容不同时重命名保留，其余文件直接替换"""
+        return cls(
+            ext=dict.fromkeys(["bms", "bml", "bme", "pms", "txt", "bmson"], ReplaceAction.CheckReplace),
+            default=ReplaceAction.Replace,
+        )
+
+    def overwrite_always(self) -> Self:
+        return replace(self, ext={}, default=ReplaceAction.Replace, update_newer_only=False, skip_if_same_size=False)
+
+    def skip_duplicates(self) -> Self:
+        return replace(self, skip_if_same_size=True)
+
+    def newer_only(self) -> Self:
+        return replace(self, update_newer_only=True)
+
+    def prefer_higher_quality(self, comparator: Callable[[Path, Path], int | None]) -> Self:
+        """同名WAV按comparator（如media.audio.compare_media_file_quality）比较，保留质量较高的一方"""
+        return replace(
+            self, ext={**self.ext, "wav": ReplaceAction.ReplaceIfHigherBitrate}, quality_comparator=comparator
+        )
 
+    def action_for(self, file_path: Path) -> ReplaceAction:
+        file_ext = file_path.suffix.lower()
+        if file_ext.startswith("."):
+            file_ext = file_ext[1:]
+        return self.ext.get(file_ext) or self.default
 
-REPLACE_OPTION_UPDATE_PACK = ReplaceOptions(
-    ext=dict.fromkeys(["bms", "bml", "bme", "pms", "txt", "bmson"], ReplaceAction.CheckReplace),
-    default=ReplaceAction.Replace,
-)
 
 DEFAULT_MOVE_OPTIONS = MoveOptions()
 DEFAULT_REPLACE_OPTIONS = ReplaceOptions()
@@ -88,10 +126,14 @@ def move_elements_across_dir(
 
     def plan_move_file(ori_path: Path, dst_path: Path) -> tuple[Path, Path] | None:
         # Replace?
-        file_ext = ori_path.suffix
-        if file_ext.startswith("."):
-            file_ext = file_ext[1:]
-        action = replace_options.ext.get(file_ext) or replace_options.default
+        action = replace_options.action_for(ori_path)
+        if dst_path.is_file():
+            ori_stat = ori_path.stat()
+            dst_stat = dst_path.stat()
+            if replace_options.skip_if_same_size and ori_stat.st_size == dst_stat.st_size:
+                return None
+            if replace_options.update_newer_only and ori_stat.st_mtime <= dst_stat.st_mtime:
+                return None
 
         def plan_move() -> tuple[Path, Path]:
             return (ori_path, dst_path)
@@ -128,6 +170,21 @@ def move_elements_across_dir(
                 return plan_move()
             case ReplaceAction.Rename:
                 return plan_move_rename()
+            case ReplaceAction.ReplaceIfNewer:
+                if dst_path.is_file() and ori_path.stat().st_mtime <= dst_path.stat().st_mtime:
+                    return None
+                return plan_move()
+            case ReplaceAction.ReplaceIfHigherBitrate:
+                if not dst_path.is_file():
+                    return plan_move()
+                quality_cmp = None
+                if replace_options.quality_comparator is not None:
+                    quality_cmp = replace_options.quality_comparator(ori_path, dst_path)
+                if quality_cmp is None:
+                    quality_cmp = 1 if ori_path.stat().st_mtime > dst_path.stat().st_mtime else -1
+                if quality_cmp <= 0:
+                    return None
+                return plan_move()
             case ReplaceAction.CheckReplace:
                 if not dst_path.is_file():
                     return plan_move()
@@ -199,3 +256,55 @@ def move_elements_across_dir(
             shutil.rmtree(dir_path_ori)
         except PermissionError:
             print(f" x PermissionError! ({dir_path_ori})")
+
+
+@dataclass
+class ThroughputResult:
+    write_mbps: float
+    read_mbps: float
+
+
+IO_BENCHMARK_FILE_NAME = ".bms_io_benchmark.tmp"
+IO_BENCHMARK_CHUNK_SIZE = 1024 * 1024
+
+
+def _write_benchmark_file(file_path: Path, size: int) -> float:
+    """写入指定大小的临时文件并落盘，返回耗时（秒）"""
+    chunk = os.urandom(IO_BENCHMARK_CHUNK_SIZE)
+    start = time.perf_counter()
+    with file_path.open("wb") as f:
+        for _ in range(size // IO_BENCHMARK_CHUNK_SIZE):
+            f.write(chunk)
+        f.flush()
+        os.fsync(f.fileno())
+    return time.perf_counter() - start
+
+
+def _read_benchmark_file(file_path: Path) -> float:
+    """读取整个文件，返回耗时（秒）。支持时先丢弃该文件的页缓存，避免读取内存中的数据"""
+    with file_path.open("rb") as f:
+        if hasattr(os, "posix_fadvise"):
+            os.posix_fadvise(f.fileno(), 0, 0, os.POSIX_FADV_DONTNEED)
+        start = time.perf_counter()
+        while f.read(IO_BENCHMARK_CHUNK_SIZE):
+            pass
+    return time.perf_counter() - start
+
+
+def measure_io_throughput(src_dir: Path, dst_dir: Path, size: int = 100 * 1024 * 1024) -> ThroughputResult:
+    """
+    在大批量移动前测量实际IO速度：向dst_dir写入临时文件测量写入速度，从src_dir读取临时文件测量读取速度。
+    临时文件在测量后删除。结果单位为MB/s。
+    """
+    dst_file = dst_dir / IO_BENCHMARK_FILE_NAME
+    src_file = src_dir / IO_BENCHMARK_FILE_NAME
+    size_mb = size / (1024 * 1024)
+    try:
+        write_seconds = _write_benchmark_file(dst_file, size)
+        if src_file != dst_file:
+            _write_benchmark_file(src_file, size)
+        read_seconds = _read_benchmark_file(src_file)
+    finally:
+        dst_file.unlink(missing_ok=True)
+        src_file.unlink(missing_ok=True)
+    return ThroughputResult(size_mb / max(write_seconds, 1e-9), size_mb / max(read_seconds, 1e-9))
@@ -1,4 +1,8 @@
+from enum import Enum
+from pathlib import Path
+
 from bms.parse import BMSInfo
+from fs import walk_bms_tree
 
 
 def get_valid_fs_name(ori_name: str) -> str:
@@ -22,3 +26,138 @@ def get_valid_fs_name(ori_name: str) -> str:
 
 def get_work_folder_name(id: str, info: BMSInfo) -> str:
     return f"{id}. {get_valid_fs_name(info.title)} [{get_valid_fs_name(info.artist)}]"
+
+
+BMS_DIFFICULTY_LABELS = ("BEGINNER", "NORMAL", "HYPER", "ANOTHER", "INSANE", "EASY", "HARD")
+
+
+def strip_difficulty_suffix_from_dir_name(name: str) -> str:
+    """移除名称末尾的难度标签，如 "Song [ANOTHER]" -> "Song"，连续的多个标签都会被移除"""
+    labels = tuple(f"[{label}]" for label in BMS_DIFFICULTY_LABELS)
+    stripped = name.rstrip()
+    while stripped.upper().endswith(labels):
+        stripped = stripped[: stripped.rindex("[")].rstrip()
+    return stripped if len(stripped) > 0 else name
+
+
+def get_name_edit_distance(name_a: str, name_b: str) -> int:
+    """两个名称之间的Levenshtein编辑距离"""
+    if len(name_a) < len(name_b):
+        name_a, name_b = name_b, name_a
+    prev_row = list(range(len(name_b) + 1))
+    for i, char_a in enumerate(name_a, start=1):
+        row = [i]
+        for j, char_b in enumerate(name_b, start=1):
+            row.append(min(prev_row[j] + 1, row[j - 1] + 1, prev_row[j - 1] + (char_a != char_b)))
+        prev_row = row
+    return prev_row[-1]
+
+
+WINDOWS_RESERVED_NAMES = frozenset(
+    ["CON", "PRN", "AUX", "NUL"] + [f"COM{i}" for i in range(1, 10)] + [f"LPT{i}" for i in range(1, 10)]
+)
+
+
+def is_windows_reserved_name(name: str) -> bool:
+    """Windows中无法创建的文件名，如 "CON"、"nul.wav"（第一个"."之前的部分为保留名）"""
+    return name.split(".", 1)[0].rstrip(" ").upper() in WINDOWS_RESERVED_NAMES
+
+
+def detect_windows_reserved_filenames(root_dir: Path) -> list[Path]:
+    """查找根目录下（递归）所有使用Windows保留名的文件和文件夹"""
+    return sorted(p for p in walk_bms_tree(root_dir) if is_windows_reserved_name(p.name))
+
+
+def rename_windows_reserved_filenames(root_dir: Path, suffix: str) -> list[tuple[Path, Path]]:
+    """
+    在保留名后追加 suffix，如 "CON.wav" -> "CON_.wav"（suffix为"_"）。目标已存在时跳过。
+    从最深层开始重命名，避免父文件夹改名后子路径失效。
+
+    :return: (原路径, 新路径) 列表
+    """
+    pairs: list[tuple[Path, Path]] = []
+    for path in sorted(detect_windows_reserved_filenames(root_dir), key=lambda p: len(p.parts), reverse=True):
+        parts = path.name.split(".", 1)
+        new_name = parts[0] + suffix + ("." + parts[1] if len(parts) > 1 else "")
+        new_path = path.with_name(new_name)
+        if new_path.exists():
+            print(f" !_! {new_path} already exists, skipping.")
+            continue
+        path.rename(new_path)
+        pairs.append((path, new_path))
+    return pairs
+
+
+# Windows的MAX_PATH为260，含末尾的空字符
+WINDOWS_MAX_PATH_LEN = 259
+
+
+def detect_long_path_names(root_dir: Path, max_len: int = WINDOWS_MAX_PATH_LEN) -> list[tuple[Path, int]]:
+    """
+    递归查找完整路径长度（字符数）超过 max_len 的文件和文件夹，复制到Windows时会失败。
+    root_dir为相对路径时按绝对路径计算长度。返回 (绝对路径, 长度) 列表，按长度降序排列。
+    """
+    paths = walk_bms_tree(root_dir.resolve())
+    results = [(path, len(str(path))) for path in paths if len(str(path)) > max_len]
+    results.sort(key=lambda x: -x[1])
+    return results
+
+
+class CaseStyle(Enum):
+    Lower = 0
+    Upper = 1
+    TitleCase = 2
+
+
+def apply_case_style(name: str, style: CaseStyle) -> str:
+    match style:
+        case CaseStyle.Lower:
+            return name.lower()
+        case CaseStyle.Upper:
+            return name.upper()
+        case CaseStyle.TitleCase:
+            return name.title()
+
+
+def rename_subdirs_case(root_dir: Path, style: CaseStyle, dry_run: bool) -> list[tuple[Path, Path]]:
+    """
+    将根目录下的子文件夹名统一为指定大小写。
+    在大小写不敏感的文件系统上，经由临时名称重命名。
+
+    :return: (原路径, 新路径) 列表
+    """
+    pairs: list[tuple[Path, Path]] = []
+    for dir_path in sorted(p for p in root_dir.iterdir() if p.is_dir()):
+        new_path = dir_path.with_name(apply_case_style(dir_path.name, style))
+        if new_path.name == dir_path.name:
+            continue
+        if new_path.exists() and not new_path.samefile(dir_path):
+            print(f" !_! {new_path} already exists, skipping.")
+            continue
+        print(f" - {dir_path.name} -> {new_path.name}")
+        pairs.append((dir_path, new_path))
+        if dry_run:
+            continue
+        temp_path = dir_path.with_name(dir_path.name + ".__case_tmp__")
+        dir_path.rename(temp_path)
+        temp_path.rename(new_path)
+    return pairs
+
+
+def assert_unique_work_dir_names(root_dir: Path) -> None:
+    """
+    检查根目录下是否存在忽略大小写后同名的子文件夹（大小写不敏感的文件系统上会互相覆盖）。
+
+    :raises ValueError: 存在冲突时
+    """
+    seen: dict[str, str] = {}
+    collisions: list[tuple[str, str]] = []
+    for dir_path in sorted(p for p in root_dir.iterdir() if p.is_dir()):
+        key = dir_path.name.casefold()
+        if key in seen:
+            collisions.append((seen[key], dir_path.name))
+        else:
+            seen[key] = dir_path.name
+    if collisions:
+        desc = ", ".join(f"{a} <-> {b}" for a, b in collisions)
+        raise ValueError(f"Dir names collide under case folding in {root_dir}: {desc}")
@@ -4,12 +4,14 @@ import shutil
 import time
 import zipfile
 from concurrent.futures import ThreadPoolExecutor, as_completed
-from pathlib import Path
+from dataclasses import dataclass, field
+from pathlib import Path, PurePosixPath
 
 import py7zr
 import rarfile  # type: ignore[import-untyped]
 
 from bms import CHART_FILE_EXTS
+from fs import walk_bms_tree
 from fs.move import move_elements_across_dir
 
 
@@ -51,32 +53,40 @@ def _try_decode_cp932_from_cp437(name: str) -> str | None:
         return None
 
 
-def unzip_zip_file_to_cache_dir(file_path: Path, cache_dir_path: Path) -> None:
-    print(f"Extracting {file_path} to {cache_dir_path} (zip)")
-    zf = zipfile.ZipFile(file_path)
-    infos = zf.infolist()
-
-    # 先判断是否需要 cp932 解码（仅对非 UTF-8 条目）
+def _zip_use_cp932(infos: list[zipfile.ZipInfo]) -> bool:
+    # 判断是否需要 cp932 解码（仅对非 UTF-8 条目）
     non_utf8_infos = [i for i in infos if (i.flag_bits & 0x800) == 0]
-    use_cp932 = False
     for i in non_utf8_infos:
         sjis = _try_decode_cp932_from_cp437(i.filename)
         if sjis is None:
             continue
         # 粗略判断是否包含常见日文/中日韩字符
         if any(("\u3040" <= ch <= "\u30ff") or ("\u3400" <= ch <= "\u9fff") for ch in sjis):
-            use_cp932 = True
-            break
+            return True
+    return False
+
+
+def _decode_zip_name(info: zipfile.ZipInfo, use_cp932: bool) -> str:
+    if (info.flag_bits & 0x800) != 0:
+        return info.filename
+    if use_cp932:
+        sjis = _try_decode_cp932_from_cp437(info.filename)
+        if sjis is not None:
+            return sjis
+    return info.filename
+
+
+def unzip_zip_file_to_cache_dir(file_path: Path, cache_dir_path: Path) -> None:
+    print(f"Extracting {file_path} to {cache_dir_path} (zip)")
+    zf = zipfile.ZipFile(file_path)
+    infos = zf.infolist()
+
+    # 先判断是否需要 cp932 解码
+    use_cp932 = _zip_use_cp932(infos)
 
     # Name 解码函数
     def decode_name(info: zipfile.ZipInfo) -> str:
-        if (info.flag_bits & 0x800) != 0:
-            return info.filename
-        if use_cp932:
-            sjis = _try_decode_cp932_from_cp437(info.filename)
-            if sjis is not None:
-                return sjis
-        return info.filename
+        return _decode_zip_name(info, use_cp932)
 
     # 单条目任务：重新打开 zip 以避免多线程共享句柄
     def extract_one(member_name: str) -> None:
@@ -104,6 +114,48 @@ def unzip_zip_file_to_cache_dir(file_path: Path, cache_dir_path: Path) -> None:
     zf.close()
 
 
+@dataclass
+class ZipValidationReport:
+    file_paths: list[str] = field(default_factory=list)
+    unsafe_paths: list[str] = field(default_factory=list)
+    total_uncompressed_size: int = 0
+    # 所有条目都位于同一个顶层目录下时，为该目录名；平铺结构时为None
+    single_top_level_dir: str | None = None
+
+
+def _is_unsafe_archive_path(name: str) -> bool:
+    path = PurePosixPath(name.replace("\\", "/"))
+    if path.is_absolute() or ".." in path.parts:
+        return True
+    # Windows 盘符，如 "C:"
+    return len(path.parts) > 0 and path.parts[0].endswith(":")
+
+
+def validate_zip_archive_contents(zip_path: Path) -> ZipValidationReport:
+    """在不解压的情况下检查ZIP文件的内容。"""
+    report = ZipValidationReport()
+    with zipfile.ZipFile(zip_path) as zf:
+        infos = zf.infolist()
+        use_cp932 = _zip_use_cp932(infos)
+        top_level_names: set[str] = set()
+        has_top_level_file = False
+        for info in infos:
+            name = _decode_zip_name(info, use_cp932)
+            report.file_paths.append(name)
+            report.total_uncompressed_size += info.file_size
+            if _is_unsafe_archive_path(name):
+                report.unsafe_paths.append(name)
+            parts = PurePosixPath(name.replace("\\", "/")).parts
+            if len(parts) == 0 or parts[0] == "__MACOSX":
+                continue
+            top_level_names.add(parts[0])
+            if len(parts) == 1 and not info.is_dir():
+                has_top_level_file = True
+    if len(top_level_names) == 1 and not has_top_level_file:
+        report.single_top_level_dir = top_level_names.pop()
+    return report
+
+
 def unzip_7z_file_to_cache_dir(file_path: Path, cache_dir_path: Path) -> None:
     print(f"Extracting {file_path} to {cache_dir_path} (7z)")
     sevenzip_file = py7zr.SevenZipFile(file_path)
@@ -135,6 +187,55 @@ def unzip_file_to_cache_dir(file_path: Path, cache_dir_path: Path) -> None:
         shutil.copy(file_path, target_file_path)
 
 
+NESTED_ARCHIVE_EXTS = (".zip", ".rar", ".7z", ".lzh")
+
+
+def find_unpacked_nested_archives(root_dir: Path) -> list[Path]:
+    """查找根目录下各作品目录中残留的压缩包，这些文件通常需要进一步解压。"""
+    archive_paths: list[Path] = []
+    for work_dir in sorted(p for p in root_dir.iterdir() if p.is_dir()):
+        for file_path in sorted(walk_bms_tree(work_dir)):
+            if file_path.is_file() and file_path.suffix.lower() in NESTED_ARCHIVE_EXTS:
+                archive_paths.append(file_path)
+    return archive_paths
+
+
+def get_archive_uncompressed_size(file_path: Path) -> int | None:
+    """读取压缩包头部，估算解压后的大小。不支持的格式或读取失败时返回None。"""
+    file_suffix = file_path.suffix.lower()
+    try:
+        if file_suffix == ".zip":
+            with zipfile.ZipFile(file_path) as zf:
+                return sum(info.file_size for info in zf.infolist())
+        if file_suffix == ".7z":
+            with py7zr.SevenZipFile(file_path) as sevenzip_file:
+                return int(sevenzip_file.archiveinfo().uncompressed)
+        if file_suffix == ".rar":
+            with rarfile.RarFile(file_path) as rar_file:
+                return sum(info.file_size for info in rar_file.infolist())
+    except Exception as e:
+        print(f" !_! {file_path}: {e}")
+    return None
+
+
+def is_zip_password_protected(file_path: Path) -> bool:
+    """ZIP中是否有条目设置了加密标志位（通用标志位的第0位）。无法读取时视为未加密。"""
+    try:
+        with zipfile.ZipFile(file_path) as zf:
+            return any(info.flag_bits & 0x1 for info in zf.infolist())
+    except (OSError, zipfile.BadZipFile):
+        return False
+
+
+def scan_for_password_protected_archives(root_dir: Path) -> list[Path]:
+    """递归查找需要密码才能解压的ZIP文件，这些文件不应被自动解压。"""
+    return sorted(
+        p
+        for p in walk_bms_tree(root_dir)
+        if p.is_file() and p.suffix.lower() == ".zip" and is_zip_password_protected(p)
+    )
+
+
 def get_num_set_file_names(pack_dir: Path) -> list[str]:
     file_id_names: list[str] = []
     for file_path in pack_dir.iterdir():
@@ -188,7 +289,7 @@ def move_out_files_in_folder_in_cache_dir(cache_dir_path: Path) -> bool:
         if cache_folder_count > 1:
             # If there are .bms chart files anywhere in cache_dir, do not error
             has_bms = False
-            for _path in cache_dir_path.rglob("*"):
+            for _path in walk_bms_tree(cache_dir_path):
                 if _path.is_file() and _path.name.lower().endswith(CHART_FILE_EXTS):
                     has_bms = True
                     break
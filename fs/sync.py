@@ -4,6 +4,8 @@ import shutil
 from enum import Enum
 from pathlib import Path
 
+from fs import walk_bms_tree
+
 
 class SoftSyncExec(Enum):
     NONE = 0
@@ -105,6 +107,37 @@ def get_file_sha512(file_path: Path) -> str:
     return ret
 
 
+def _get_work_dir_files(work_dir: Path) -> list[Path]:
+    return sorted((p for p in work_dir.iterdir() if p.is_file()), key=lambda p: p.name)
+
+
+def hash_work_dir_manifest(work_dir: Path) -> bytes:
+    """
+    根据作品目录第一层的 (文件名, 文件大小) 列表计算SHA-256，不读取文件内容。
+    速度快，但无法区分大小相同、内容不同的文件；需要更可靠的结果时使用 hash_work_dir_contents。
+    """
+    h = hashlib.sha256()
+    for file_path in _get_work_dir_files(work_dir):
+        h.update(file_path.name.encode("utf-8"))
+        h.update(b"\0")
+        h.update(str(file_path.stat().st_size).encode("ascii"))
+        h.update(b"\0")
+    return h.digest()
+
+
+def hash_work_dir_contents(work_dir: Path) -> bytes:
+    """根据作品目录第一层的文件名和文件内容计算SHA-256"""
+    h = hashlib.sha256()
+    for file_path in _get_work_dir_files(work_dir):
+        h.update(file_path.name.encode("utf-8"))
+        h.update(b"\0")
+        with file_path.open("rb") as f:
+            while chunk := f.read(1024 * 1024):
+                h.update(chunk)
+        h.update(b"\0")
+    return h.digest()
+
+
 SYNC_PRESET_DEFAULT = SoftSyncPreset()
 SYNC_PRESET_FOR_APPEND = SoftSyncPreset(
     name="同步预设（用于更新包）",
@@ -273,3 +306,30 @@ def sync_folder(
             print(f"Dst remove: {_dst_remove_files}")
         if len(_dst_remove_dirs) > 0:
             print(f"Dst remove dir: {_dst_remove_dirs}")
+
+
+def copy_pack_with_hardlinks(src_dir: Path, dst_dir: Path) -> int:
+    """
+    以硬链接的方式复制整个目录，不复制文件内容。
+    无法创建硬链接时（如跨文件系统、文件系统不支持），改为普通复制。目标中已存在的文件会被跳过。
+
+    :return: 链接或复制的总字节数
+    """
+    total_size = 0
+    for src_path in walk_bms_tree(src_dir):
+        dst_path = dst_dir / src_path.relative_to(src_dir)
+        if src_path.is_dir():
+            dst_path.mkdir(parents=True, exist_ok=True)
+            continue
+        if not src_path.is_file() or src_path.is_symlink():
+            continue
+        if dst_path.exists():
+            print(f" !_! {dst_path} already exists, skipping.")
+            continue
+        dst_path.parent.mkdir(parents=True, exist_ok=True)
+        try:
+            os.link(src_path, dst_path)
+        except OSError:
+            shutil.copy2(src_path, dst_path)
+        total_size += src_path.stat().st_size
+    return total_size
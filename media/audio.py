@@ -1,7 +1,13 @@
 import multiprocessing
+import os
+import struct
 import subprocess
 import time
+from dataclasses import dataclass, field
 from pathlib import Path
+from typing import BinaryIO
+
+from fs import walk_bms_tree
 
 """
 Audio
@@ -277,3 +283,452 @@ def bms_folder_transfer_audio(
             print(" - Dir:", bms_dir_path, "Error occured!")
             if stop_on_error:
                 break
+
+
+"""
+WAV Info
+"""
+
+WAV_FORMAT_PCM = 0x0001
+WAV_FORMAT_IEEE_FLOAT = 0x0003
+WAV_FORMAT_EXTENSIBLE = 0xFFFE
+
+
+@dataclass
+class WavFormat:
+    audio_format: int
+    channels: int
+    sample_rate: int
+    bits_per_sample: int
+    data_offset: int
+    data_size: int
+
+    @property
+    def block_align(self) -> int:
+        return self.channels * self.bits_per_sample // 8
+
+
+def read_wav_format(file_path: Path) -> WavFormat | None:
+    """仅读取RIFF头，获取WAV格式信息。不是WAV文件或头部损坏时返回None。"""
+    with file_path.open("rb") as f:
+        header = f.read(12)
+        if len(header) < 12 or header[0:4] != b"RIFF" or header[8:12] != b"WAVE":
+            return None
+        fmt: tuple[int, int, int, int] | None = None
+        while True:
+            chunk_header = f.read(8)
+            if len(chunk_header) < 8:
+                return None
+            chunk_id, chunk_size = struct.unpack("<4sI", chunk_header)
+            if chunk_id == b"fmt ":
+                chunk = f.read(chunk_size)
+                if len(chunk) < 16:
+                    return None
+                audio_format, channels, sample_rate, _byte_rate, _block_align, bits = struct.unpack(
+                    "<HHIIHH", chunk[:16]
+                )
+                # WAVE_FORMAT_EXTENSIBLE：真实格式在SubFormat GUID的前两个字节
+                if audio_format == WAV_FORMAT_EXTENSIBLE and len(chunk) >= 26:
+                    audio_format = struct.unpack("<H", chunk[24:26])[0]
+                fmt = (audio_format, channels, sample_rate, bits)
+            elif chunk_id == b"data":
+                if fmt is None:
+                    return None
+                return WavFormat(*fmt, data_offset=f.tell(), data_size=chunk_size)
+            else:
+                f.seek(chunk_size, 1)
+            # 块按偶数字节对齐
+            if chunk_size % 2 == 1:
+                f.seek(1, 1)
+
+
+def compare_media_file_quality(file_a: Path, file_b: Path) -> int | None:
+    """
+    按 位深 × 采样率 比较两个WAV文件的质量。
+    返回值 >0：a更好；<0：b更好；0：相同；无法判断时返回None。
+    """
+    format_a = read_wav_format(file_a) if file_a.is_file() else None
+    format_b = read_wav_format(file_b) if file_b.is_file() else None
+    if format_a is None or format_b is None:
+        return None
+    quality_a = format_a.bits_per_sample * format_a.sample_rate
+    quality_b = format_b.bits_per_sample * format_b.sample_rate
+    return (quality_a > quality_b) - (quality_a < quality_b)
+
+
+def _decode_wav_sample(sample: bytes, wav_format: WavFormat) -> float | None:
+    """将单个采样解码为 -1.0 ~ 1.0 范围内的值"""
+    bits = wav_format.bits_per_sample
+    if wav_format.audio_format == WAV_FORMAT_IEEE_FLOAT and bits == 32:
+        return float(struct.unpack("<f", sample)[0])
+    if wav_format.audio_format != WAV_FORMAT_PCM:
+        return None
+    if bits == 8:
+        # 8位PCM为无符号数
+        return (sample[0] - 128) / 128
+    if bits in (16, 24, 32):
+        return int.from_bytes(sample, "little", signed=True) / (1 << (bits - 1))
+    return None
+
+
+def get_wav_peak(file_path: Path, frame_step: int = 100) -> float | None:
+    """
+    每隔frame_step帧采样一次，获取WAV文件的峰值振幅（0.0 ~ 1.0）。
+    不支持的格式返回None。
+    """
+    wav_format = read_wav_format(file_path)
+    if wav_format is None or wav_format.block_align == 0:
+        return None
+    sample_width = wav_format.bits_per_sample // 8
+    with file_path.open("rb") as f:
+        f.seek(wav_format.data_offset)
+        data = f.read(wav_format.data_size)
+    frame_count = len(data) // wav_format.block_align
+    peak = 0.0
+    for frame_index in range(0, frame_count, max(1, frame_step)):
+        frame_offset = frame_index * wav_format.block_align
+        for channel in range(wav_format.channels):
+            offset = frame_offset + channel * sample_width
+            value = _decode_wav_sample(data[offset : offset + sample_width], wav_format)
+            if value is None:
+                return None
+            peak = max(peak, abs(value))
+    return peak
+
+
+def detect_audio_clipping(work_dir: Path, threshold: float, frame_step: int = 100) -> list[tuple[Path, float]]:
+    """
+    查找作品目录中峰值振幅超过threshold（0.0 ~ 1.0）的WAV文件。
+    返回 (文件路径, 峰值) 列表。
+    """
+    clipped_files: list[tuple[Path, float]] = []
+    for file_path in work_dir.iterdir():
+        if not file_path.is_file() or file_path.suffix.lower() != ".wav":
+            continue
+        peak = get_wav_peak(file_path, frame_step)
+        if peak is None:
+            continue
+        if peak > threshold:
+            clipped_files.append((file_path, peak))
+    return clipped_files
+
+
+def detect_sample_rate_mismatch(work_dir: Path, expected_hz: int) -> list[tuple[Path, int]]:
+    """
+    读取作品目录中每个WAV文件头部的采样率，返回与expected_hz（通常为44100）不一致的 (文件路径, 采样率)。
+    不解码音频数据。
+    """
+    mismatched_files: list[tuple[Path, int]] = []
+    for file_path in sorted(work_dir.iterdir()):
+        if not file_path.is_file() or file_path.suffix.lower() != ".wav":
+            continue
+        wav_format = read_wav_format(file_path)
+        if wav_format is not None and wav_format.sample_rate != expected_hz:
+            mismatched_files.append((file_path, wav_format.sample_rate))
+    return mismatched_files
+
+
+def verify_bms_wav_stereo_mono(work_dir: Path) -> list[tuple[Path, int]]:
+    """
+    读取作品目录中每个WAV文件头部的声道数，返回多于1个声道的 (文件路径, 声道数)。
+    按键音通常为单声道，误存为立体声的文件可以减半体积。
+    """
+    multi_channel_files: list[tuple[Path, int]] = []
+    for file_path in sorted(work_dir.iterdir()):
+        if not file_path.is_file() or file_path.suffix.lower() != ".wav":
+            continue
+        wav_format = read_wav_format(file_path)
+        if wav_format is not None and wav_format.channels > 1:
+            multi_channel_files.append((file_path, wav_format.channels))
+    return multi_channel_files
+
+
+"""
+FLAC Check
+"""
+
+
+def check_flac_integrity(work_dir: Path) -> list[tuple[Path, str]]:
+    """
+    使用 "flac -t" 完整解码作品目录中的每个FLAC文件。
+    返回 (文件路径, 错误信息) 列表，仅包含解码失败的文件。
+    """
+    corrupted_files: list[tuple[Path, str]] = []
+    for file_path in sorted(work_dir.iterdir()):
+        if not file_path.is_file() or file_path.suffix.lower() != ".flac":
+            continue
+        result = subprocess.run(["flac", "-t", "-s", str(file_path)], capture_output=True, text=True, errors="replace")
+        if result.returncode != 0:
+            corrupted_files.append((file_path, result.stderr.strip()))
+    return corrupted_files
+
+
+def verify_media_file_not_corrupt(file_path: Path) -> bool:
+    """
+    只检查文件头的标识：OGG为 "OggS"，FLAC为 "fLaC"，WAV为 "RIFF" + "WAVE"。
+    其他扩展名视为通过。不进行解码，只能发现明显损坏的文件。
+    """
+    with file_path.open("rb") as f:
+        header = f.read(12)
+    match file_path.suffix.lower():
+        case ".ogg":
+            return header[0:4] == b"OggS"
+        case ".flac":
+            return header[0:4] == b"fLaC"
+        case ".wav":
+            return header[0:4] == b"RIFF" and header[8:12] == b"WAVE"
+        case _:
+            return True
+
+
+def find_corrupt_media_files(work_dir: Path) -> list[Path]:
+    """作品目录中文件头检查不通过的音频文件"""
+    return [
+        file_path
+        for file_path in sorted(work_dir.iterdir())
+        if file_path.is_file() and not verify_media_file_not_corrupt(file_path)
+    ]
+
+
+"""
+WAV → FLAC Alias
+"""
+
+
+def _replace_wav_with_flac_data(wav_path: Path, encoder_path: Path) -> bool:
+    """将WAV重新编码为FLAC，并以原.wav文件名保存"""
+    tmp_path = wav_path.with_name(f"{wav_path.name}.flac.tmp")
+    result = subprocess.run(
+        [str(encoder_path), "--best", "-f", "-s", "-o", str(tmp_path), str(wav_path)],
+        capture_output=True,
+        text=True,
+        errors="replace",
+    )
+    if result.returncode != 0 or not tmp_path.is_file():
+        print(f" !_! {wav_path}: {result.stderr.strip()}")
+        tmp_path.unlink(missing_ok=True)
+        return False
+    tmp_path.replace(wav_path)
+    return True
+
+
+def merge_wav_into_flac(work_dir: Path, encoder_path: Path, dry_run: bool) -> int:
+    """
+    对作品目录中存在同名FLAC的WAV文件，保留BMS中的 "#WAVxx kick.wav" 声明不变，但以FLAC格式存储数据：
+    优先将 kick.wav 替换为指向 kick.flac 的符号链接；不支持符号链接时（如没有权限的Windows），
+    使用 encoder_path（flac）将 kick.wav 重新编码为FLAC数据并保持文件名。
+
+    :return: 处理（dry_run时为将处理）的文件数量
+    """
+    flac_paths = {p.stem.lower(): p for p in work_dir.iterdir() if p.is_file() and p.suffix.lower() == ".flac"}
+    processed_count = 0
+    for wav_path in sorted(work_dir.iterdir()):
+        if wav_path.suffix.lower() != ".wav" or wav_path.is_symlink() or not wav_path.is_file():
+            continue
+        flac_path = flac_paths.get(wav_path.stem.lower())
+        if flac_path is None:
+            continue
+        if dry_run:
+            print(f" - {wav_path.name} -> {flac_path.name}")
+            processed_count += 1
+            continue
+        backup_path = wav_path.with_name(f"{wav_path.name}.bak")
+        wav_path.rename(backup_path)
+        try:
+            os.symlink(flac_path.name, wav_path)
+        except OSError:
+            backup_path.rename(wav_path)
+            if not _replace_wav_with_flac_data(wav_path, encoder_path):
+                continue
+        else:
+            backup_path.unlink()
+        processed_count += 1
+    return processed_count
+
+
+"""
+WAV → FLAC Compression
+"""
+
+
+@dataclass
+class CompressionReport:
+    # 作品目录 -> 已压缩为FLAC的WAV文件
+    compressed: dict[Path, list[Path]] = field(default_factory=dict)
+    # 压缩后体积变小的文件所节省的字节数
+    bytes_saved: int = 0
+    # 压缩后体积反而变大的文件所增加的字节数
+    bytes_grown: int = 0
+    failed: list[Path] = field(default_factory=list)
+
+
+def compress_pack_wav_with_flac(root_dir: Path, flac_encoder_path: Path, delete_original: bool) -> CompressionReport:
+    """
+    将根目录下各作品目录（含子文件夹）中的WAV无损压缩为FLAC。已存在同名FLAC的WAV不处理。
+    不修改BMS文件，调用方需根据report.compressed更新引用。
+    """
+    report = CompressionReport()
+    for work_dir in sorted(p for p in root_dir.iterdir() if p.is_dir()):
+        wav_paths = sorted(p for p in walk_bms_tree(work_dir) if p.suffix.lower() == ".wav" and p.is_file())
+        for wav_path in wav_paths:
+            flac_path = wav_path.with_suffix(".flac")
+            if flac_path.exists():
+                print(f" !_! {flac_path} already exists, skipping.")
+                continue
+            result = subprocess.run(
+                [str(flac_encoder_path), "--best", "-s", "-o", str(flac_path), str(wav_path)],
+                capture_output=True,
+                text=True,
+                errors="replace",
+            )
+            if result.returncode != 0 or not flac_path.is_file():
+                print(f" !_! {wav_path}: {result.stderr.strip()}")
+                flac_path.unlink(missing_ok=True)
+                report.failed.append(wav_path)
+                continue
+            report.compressed.setdefault(work_dir, []).append(wav_path)
+            size_diff = wav_path.stat().st_size - flac_path.stat().st_size
+            if size_diff >= 0:
+                report.bytes_saved += size_diff
+            else:
+                report.bytes_grown -= size_diff
+            if delete_original:
+                wav_path.unlink()
+    return report
+
+
+"""
+Metadata Tags
+"""
+
+
+def _wav_has_tags(f: BinaryIO) -> bool:
+    header = f.read(12)
+    if len(header) < 12 or header[0:4] != b"RIFF" or header[8:12] != b"WAVE":
+        return False
+    while True:
+        chunk_header = f.read(8)
+        if len(chunk_header) < 8:
+            return False
+        chunk_id, chunk_size = struct.unpack("<4sI", chunk_header)
+        if chunk_id.lower() == b"id3 ":
+            return True
+        chunk_start = f.tell()
+        if chunk_id == b"LIST" and f.read(4) == b"INFO":
+            return True
+        # 块按偶数字节对齐
+        f.seek(chunk_start + chunk_size + chunk_size % 2)
+
+
+def _flac_has_tags(f: BinaryIO) -> bool:
+    if f.read(4) != b"fLaC":
+        return False
+    while True:
+        block_header = f.read(4)
+        if len(block_header) < 4:
+            return False
+        is_last = block_header[0] & 0x80 != 0
+        block_type = block_header[0] & 0x7F
+        block_size = int.from_bytes(block_header[1:4], "big")
+        # 6: PICTURE
+        if block_type == 6:
+            return True
+        # 4: VORBIS_COMMENT
+        if block_type == 4:
+            block = f.read(block_size)
+            vendor_length = struct.unpack("<I", block[0:4])[0]
+            comment_count = struct.unpack("<I", block[4 + vendor_length : 8 + vendor_length])[0]
+            return comment_count > 0
+        if is_last:
+            return False
+        f.seek(block_size, 1)
+
+
+def _ogg_has_tags(f: BinaryIO) -> bool:
+    # 注释头通常位于前几页中
+    data = f.read(65536)
+    if data[0:4] != b"OggS":
+        return False
+    for marker in (b"\x03vorbis", b"OpusTags"):
+        index = data.find(marker)
+        if index < 0:
+            continue
+        offset = index + len(marker)
+        vendor_length = struct.unpack("<I", data[offset : offset + 4])[0]
+        offset += 4 + vendor_length
+        comments = data[offset : offset + 4]
+        return len(comments) == 4 and struct.unpack("<I", comments)[0] > 0
+    return False
+
+
+def has_audio_metadata_tags(file_path: Path) -> bool:
+    """文件中是否嵌入了标签：WAV的LIST/INFO或ID3块，FLAC/OGG的Vorbis注释或封面，以及ID3v2头"""
+    try:
+        with file_path.open("rb") as f:
+            if f.read(3) == b"ID3":
+                return True
+            f.seek(0)
+            match file_path.suffix.lower():
+                case ".wav":
+                    return _wav_has_tags(f)
+                case ".flac":
+                    return _flac_has_tags(f)
+                case ".ogg":
+                    return _ogg_has_tags(f)
+                case _:
+                    return False
+    except struct.error:
+        return False
+
+
+def strip_audio_metadata_tags(file_path: Path) -> bool:
+    """使用 "ffmpeg -map_metadata -1 -c copy" 去除标签（只保留音频流，封面也会被去除），成功后替换原文件"""
+    tmp_path = file_path.with_name(f"{file_path.stem}.notag{file_path.suffix}")
+    result = subprocess.run(
+        [
+            "ffmpeg",
+            "-hide_banner",
+            "-loglevel",
+            "error",
+            "-y",
+            "-i",
+            str(file_path),
+            "-map",
+            "0:a",
+            "-map_metadata",
+            "-1",
+            "-fflags",
+            "+bitexact",
+            "-c",
+            "copy",
+            str(tmp_path),
+        ],
+        capture_output=True,
+        text=True,
+        errors="replace",
+    )
+    if result.returncode != 0 or not tmp_path.is_file():
+        print(f" !_! {file_path}: {result.stderr.strip()}")
+        tmp_path.unlink(missing_ok=True)
+        return False
+    tmp_path.replace(file_path)
+    return True
+
+
+def batch_remove_audio_metadata_tags(root_dir: Path, dry_run: bool) -> int:
+    """
+    去除根目录下各作品目录中WAV/OGG/FLAC文件的嵌入标签。
+
+    :return: 处理（dry_run时为将处理）的文件数量
+    """
+    processed_count = 0
+    for work_dir in sorted(p for p in root_dir.iterdir() if p.is_dir()):
+        for file_path in sorted(work_dir.iterdir()):
+            if not file_path.is_file() or file_path.suffix.lower() not in (".wav", ".ogg", ".flac"):
+                continue
+            if not has_audio_metadata_tags(file_path):
+                continue
+            print(f" - {file_path}")
+            if dry_run or strip_audio_metadata_tags(file_path):
+                processed_count += 1
+    return processed_count
@@ -0,0 +1,270 @@
+import random
+import struct
+import subprocess
+from pathlib import Path
+from typing import BinaryIO
+
+from bms import IMAGE_FILE_EXTS, VIDEO_FILE_EXTS, get_dir_encoding
+from bms.check import MediaFileLookup
+from bms.edit import get_dir_bms_file_paths, replace_bms_file_reference_ext, set_bms_header_value
+from bms.parse import match_bms_header, parse_bms_slot_definitions, read_bms_file_str
+from fs import walk_bms_tree
+
+"""
+Image Info
+"""
+
+
+def _read_jpeg_size(f: BinaryIO) -> tuple[int, int] | None:
+    """依次跳过JPEG的各个段，从SOF段读取尺寸"""
+    f.seek(2)
+    while True:
+        marker = f.read(2)
+        if len(marker) < 2 or marker[0] != 0xFF:
+            return None
+        # 填充字节
+        while marker[1] == 0xFF:
+            next_byte = f.read(1)
+            if len(next_byte) == 0:
+                return None
+            marker = marker[1:] + next_byte
+        length_bytes = f.read(2)
+        if len(length_bytes) < 2:
+            return None
+        length = struct.unpack(">H", length_bytes)[0]
+        # SOF0-SOF15，不含DHT(C4)、JPG(C8)、DAC(CC)
+        if 0xC0 <= marker[1] <= 0xCF and marker[1] not in (0xC4, 0xC8, 0xCC):
+            sof = f.read(5)
+            if len(sof) < 5:
+                return None
+            height, width = struct.unpack(">HH", sof[1:5])
+            return width, height
+        f.seek(length - 2, 1)
+
+
+def read_image_size(file_path: Path) -> tuple[int, int] | None:
+    """仅读取文件头，获取PNG（IHDR）、BMP（BITMAPINFOHEADER）或JPEG（SOF）图片的 (宽, 高)。其他格式返回None。"""
+    with file_path.open("rb") as f:
+        header = f.read(26)
+        if header.startswith(b"\xff\xd8"):
+            return _read_jpeg_size(f)
+    if header.startswith(b"\x89PNG\r\n\x1a\n") and header[12:16] == b"IHDR" and len(header) >= 24:
+        width, height = struct.unpack(">II", header[16:24])
+        return width, height
+    if header.startswith(b"BM") and len(header) >= 26:
+        dib_header_size = struct.unpack("<I", header[14:18])[0]
+        if dib_header_size == 12:
+            # BITMAPCOREHEADER
+            width, height = struct.unpack("<HH", header[18:22])
+            return width, height
+        width, height = struct.unpack("<ii", header[18:26])
+        # 高度为负表示自上而下存储
+        return width, abs(height)
+    return None
+
+
+"""
+BMP
+"""
+
+
+def scan_for_large_uncompressed_bmp(root_dir: Path, size_limit: int) -> list[tuple[Path, int]]:
+    """查找根目录下大小超过 size_limit 字节的BMP文件，按大小降序排列。"""
+    results: list[tuple[Path, int]] = []
+    for file_path in walk_bms_tree(root_dir):
+        if not file_path.is_file() or file_path.suffix.lower() != ".bmp":
+            continue
+        file_size = file_path.stat().st_size
+        if file_size > size_limit:
+            results.append((file_path, file_size))
+    results.sort(key=lambda item: item[1], reverse=True)
+    return results
+
+
+def auto_convert_bmp_to_png(file_path: Path) -> bool:
+    """
+    使用ffmpeg将BMP文件转换为PNG，并更新同目录BMS文件中的引用。
+    转换成功后删除原BMP文件；目标PNG已存在时不处理。
+
+    :return: 是否转换成功
+    """
+    png_path = file_path.with_suffix(".png")
+    if png_path.exists():
+        print(f" !_! {png_path} already exists, skipping.")
+        return False
+    result = subprocess.run(
+        ["ffmpeg", "-hide_banner", "-loglevel", "error", "-i", str(file_path), str(png_path)],
+        capture_output=True,
+    )
+    if result.returncode != 0 or not png_path.is_file():
+        print(f" !_! {file_path}: {result.stderr.decode('utf-8', errors='ignore').strip()}")
+        png_path.unlink(missing_ok=True)
+        return False
+    changed_count = replace_bms_file_reference_ext(file_path.parent, file_path.name, ".png")
+    file_path.unlink()
+    print(f" - {file_path.name} -> {png_path.name}, {changed_count} reference(s) updated")
+    return True
+
+
+"""
+Stage File
+"""
+
+STAGE_FILE_NAME = "stagefile.png"
+
+
+def _get_stage_file_source(lookup: MediaFileLookup, bga_names: list[str]) -> Path | None:
+    """优先使用BGA视频的第一帧，其次随机选取一张#BMP图片"""
+    bga_paths = [path for path in (lookup.get_path(name) for name in bga_names) if path is not None]
+    video_paths = sorted({path for path in bga_paths if path.suffix.lower() in VIDEO_FILE_EXTS})
+    if len(video_paths) > 0:
+        return video_paths[0]
+    image_paths = sorted({path for path in bga_paths if path.suffix.lower() in IMAGE_FILE_EXTS})
+    if len(image_paths) > 0:
+        return random.choice(image_paths)
+    return None
+
+
+def generate_missing_stage_file(work_dir: Path, ffmpeg_path: Path, size: tuple[int, int]) -> bool:
+    """
+    为缺少#STAGEFILE（或引用的文件不存在）的BMS文件生成占位图片，并写入#STAGEFILE。
+    图片取自BGA视频的第一帧或随机一张#BMP图片，使用ffmpeg缩放到 size (宽, 高)。
+
+    已存在 stagefile.png 时不重新生成，仅写入#STAGEFILE。
+
+    :return: 是否生成了图片或修改了BMS文件
+    """
+    encoding = get_dir_encoding(work_dir)
+    lookup = MediaFileLookup(work_dir)
+    bms_files_to_fix: list[Path] = []
+    bga_names: list[str] = []
+    for bms_file in get_dir_bms_file_paths(work_dir):
+        file_str = read_bms_file_str(bms_file, encoding)
+        stage_file: str | None = None
+        for line in file_str.splitlines():
+            stage_file = match_bms_header(line, "STAGEFILE") or stage_file
+        if stage_file is not None and lookup.exists(stage_file):
+            continue
+        bms_files_to_fix.append(bms_file)
+        bga_names.extend(parse_bms_slot_definitions(file_str, "BMP").values())
+    if len(bms_files_to_fix) == 0:
+        return False
+
+    stage_file_path = work_dir / STAGE_FILE_NAME
+    if not stage_file_path.is_file():
+        source_path = _get_stage_file_source(lookup, bga_names)
+        if source_path is None:
+            print(f" !_! {work_dir}: No BGA file to generate stage file from.")
+            return False
+        width, height = size
+        result = subprocess.run(
+            [
+                str(ffmpeg_path),
+                "-hide_banner",
+                "-loglevel",
+                "error",
+                "-i",
+                str(source_path),
+                "-frames:v",
+                "1",
+                "-vf",
+                f"scale={width}:{height}",
+                str(stage_file_path),
+            ],
+            capture_output=True,
+        )
+        if result.returncode != 0 or not stage_file_path.is_file():
+            print(f" !_! {source_path}: {result.stderr.decode('utf-8', errors='ignore').strip()}")
+            stage_file_path.unlink(missing_ok=True)
+            return False
+        print(f" - {work_dir.name}: {source_path.name} -> {STAGE_FILE_NAME}")
+    for bms_file in bms_files_to_fix:
+        set_bms_header_value(bms_file, "STAGEFILE", STAGE_FILE_NAME.encode("ascii"))
+    return True
+
+
+"""
+Banner
+"""
+
+BMS_BANNER_SIZE = (304, 80)
+
+
+def check_bms_banner_aspect_ratio(work_dir: Path) -> list[tuple[Path, int, int]]:
+    """
+    检查作品目录中BMS文件#BANNER引用的图片尺寸。
+    返回尺寸不是标准尺寸的图片 (路径, 宽, 高)；无法读取尺寸的图片不计入。
+    """
+    encoding = get_dir_encoding(work_dir)
+    lookup = MediaFileLookup(work_dir)
+    banner_paths: set[Path] = set()
+    for bms_file in get_dir_bms_file_paths(work_dir):
+        for line in read_bms_file_str(bms_file, encoding).splitlines():
+            banner = match_bms_header(line, "BANNER")
+            if banner is None:
+                continue
+            banner_path = lookup.get_path(banner)
+            if banner_path is not None:
+                banner_paths.add(banner_path)
+
+    results: list[tuple[Path, int, int]] = []
+    for banner_path in sorted(banner_paths):
+        size = read_image_size(banner_path)
+        if size is not None and size != BMS_BANNER_SIZE:
+            results.append((banner_path, *size))
+    return results
+
+
+"""
+Stage Image Size
+"""
+
+
+def batch_crop_stage_images(root_dir: Path, ffmpeg_path: Path, target: tuple[int, int]) -> int:
+    """
+    检查根目录下各作品目录中#STAGEFILE引用的图片尺寸（仅读取文件头），与 target (宽, 高) 不同时，
+    使用ffmpeg等比缩放并加黑边填充到 target，覆盖原文件。无法读取尺寸的格式不处理。
+
+    :return: 调整尺寸的图片数量
+    """
+    width, height = target
+    resized_count = 0
+    for work_dir in sorted(p for p in root_dir.iterdir() if p.is_dir()):
+        encoding = get_dir_encoding(work_dir)
+        lookup = MediaFileLookup(work_dir)
+        stage_paths: set[Path] = set()
+        for bms_file in get_dir_bms_file_paths(work_dir):
+            for line in read_bms_file_str(bms_file, encoding).splitlines():
+                stage_file = match_bms_header(line, "STAGEFILE")
+                stage_path = lookup.get_path(stage_file) if stage_file else None
+                if stage_path is not None:
+                    stage_paths.add(stage_path)
+        for stage_path in sorted(stage_paths):
+            size = read_image_size(stage_path)
+            if size is None or size == target:
+                continue
+            tmp_path = stage_path.with_name(f"{stage_path.stem}.resized{stage_path.suffix}")
+            result = subprocess.run(
+                [
+                    str(ffmpeg_path),
+                    "-hide_banner",
+                    "-loglevel",
+                    "error",
+                    "-y",
+                    "-i",
+                    str(stage_path),
+                    "-vf",
+                    f"scale={width}:{height}:force_original_aspect_ratio=decrease,"
+                    f"pad={width}:{height}:(ow-iw)/2:(oh-ih)/2",
+                    str(tmp_path),
+                ],
+                capture_output=True,
+            )
+            if result.returncode != 0 or not tmp_path.is_file():
+                print(f" !_! {stage_path}: {result.stderr.decode('utf-8', errors='ignore').strip()}")
+                tmp_path.unlink(missing_ok=True)
+                continue
+            tmp_path.replace(stage_path)
+            print(f" - {stage_path}: {size[0]}x{size[1]} -> {width}x{height}")
+            resized_count += 1
+    return resized_count
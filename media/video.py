@@ -61,6 +61,36 @@ def get_video_size(file_path: Path) -> tuple[int, int] | None:
     return None
 
 
+BGA_VIDEO_EXTS = (".mp4", ".avi", ".wmv", ".mpg", ".mpeg", ".webm")
+
+
+def detect_bga_video_codec(work_dir: Path, ffprobe_path: Path) -> list[tuple[Path, str, str]]:
+    """
+    使用ffprobe获取作品目录中BGA视频的编码，用于检查播放器兼容性。
+    返回 (视频路径, 视频编码, 音频编码) 列表，没有对应的流时编码为空字符串。
+    """
+    results: list[tuple[Path, str, str]] = []
+    for file_path in sorted(work_dir.iterdir()):
+        if not file_path.is_file() or file_path.suffix.lower() not in BGA_VIDEO_EXTS:
+            continue
+        result = subprocess.run(
+            [str(ffprobe_path), "-v", "quiet", "-print_format", "json", "-show_streams", str(file_path)],
+            capture_output=True,
+            text=True,
+            errors="replace",
+        )
+        if result.returncode != 0:
+            print(f" !_! {file_path}: ffprobe failed")
+            continue
+        codecs = {"video": "", "audio": ""}
+        for stream in json.loads(result.stdout).get("streams") or []:
+            codec_type = stream.get("codec_type")
+            if codec_type in codecs and len(codecs[codec_type]) == 0:
+                codecs[codec_type] = str(stream.get("codec_name") or "")
+        results.append((file_path, codecs["video"], codecs["audio"]))
+    return results
+
+
 """
 Video
 """
@@ -140,7 +140,7 @@ class ConfirmType(Enum):
 
 @dataclass
 class Option:
-    func: Callable[..., None]
+    func: Callable[..., object]
     name: str = ""
     inputs: list[Input] = field(default_factory=list)
     check_func: Callable[..., bool] | list[Callable[..., bool]] | None = None
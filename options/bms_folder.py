@@ -1,11 +1,25 @@
 import difflib
 import shutil
+import sqlite3
 from pathlib import Path
 
 from bms import MEDIA_FILE_EXTS, BMSInfo, get_dir_bms_info
-from fs import bms_dir_similarity
-from fs.move import REPLACE_OPTION_UPDATE_PACK, move_elements_across_dir
-from fs.name import get_valid_fs_name
+from bms.collection import (
+    BMS_INDEX_DB_NAME,
+    BmsHeaderKey,
+    export_bms_to_csv,
+    find_duplicate_works,
+    index_bms_collection,
+    query_bms_collection,
+)
+from fs import bms_dir_similarity, walk_bms_tree
+from fs.move import ReplaceOptions, move_elements_across_dir
+from fs.name import (
+    get_valid_fs_name,
+    rename_windows_reserved_filenames,
+    strip_difficulty_suffix_from_dir_name,
+)
+from fs.rawpack import find_unpacked_nested_archives, get_archive_uncompressed_size
 from options import Input, InputType, Option, is_root_dir
 
 
@@ -137,7 +151,7 @@ def _workdir_set_name_by_bms(work_dir: Path) -> bool:
     move_elements_across_dir(
         work_dir,
         new_dir_path,
-        replace_options=REPLACE_OPTION_UPDATE_PACK,
+        replace_options=ReplaceOptions.update_pack(),
     )
     return True
 
@@ -204,6 +218,14 @@ def scan_folder_similar_folders(root_dir: Path, similarity_trigger: float = 0.7)
         print(f"发现相似项：{former_dir_name} <=> {dir_name}")
 
 
+def scan_folder_duplicate_works(root_dir: Path) -> None:
+    """按谱面内容扫描重复的作品，与文件夹名无关。"""
+    duplicate_groups = find_duplicate_works(root_dir)
+    for dirs in duplicate_groups:
+        print("发现重复作品：" + " <=> ".join(d.name for d in dirs))
+    print(f"共{len(duplicate_groups)}组重复作品。")
+
+
 def undo_set_name(root_dir: Path) -> None:
     for dir_name in [p.name for p in root_dir.iterdir()]:
         dir_path = root_dir / dir_name
@@ -227,6 +249,90 @@ def undo_set_name(root_dir: Path) -> None:
         shutil.move(dir_path, new_dir_path)
 
 
+def batch_strip_difficulty_suffixes(root_dir: Path, dry_run: bool = False) -> list[tuple[Path, Path]]:
+    """
+    移除根目录下各作品文件夹名末尾的难度标签（[HYPER]、[ANOTHER]等）。
+    目标文件夹已存在时跳过。
+
+    :return: (原路径, 新路径) 列表；dry_run时仅返回，不重命名
+    """
+    pairs: list[tuple[Path, Path]] = []
+    for dir_path in sorted(p for p in root_dir.iterdir() if p.is_dir()):
+        new_dir_name = strip_difficulty_suffix_from_dir_name(dir_path.name)
+        if new_dir_name == dir_path.name:
+            continue
+        new_dir_path = root_dir / new_dir_name
+        if new_dir_path.exists():
+            print(f"Warning: Target {new_dir_path} already exists! Skipping {dir_path.name}")
+            continue
+        pairs.append((dir_path, new_dir_path))
+        if not dry_run:
+            shutil.move(dir_path, new_dir_path)
+    return pairs
+
+
+def strip_difficulty_suffixes(root_dir: Path) -> None:
+    pairs = batch_strip_difficulty_suffixes(root_dir, dry_run=True)
+    if len(pairs) == 0:
+        print("Nothing to rename.")
+        return
+    for dir_path, new_dir_path in pairs:
+        print(f"- Ready to rename: {dir_path.name} -> {new_dir_path.name}")
+    selection = input("Do renaming? [y/N]:")
+    if not selection.lower().startswith("y"):
+        print("Aborted.")
+        return
+    batch_strip_difficulty_suffixes(root_dir)
+
+
+def rename_brackets_to_parentheses(root_dir: Path, dry_run: bool = False) -> list[tuple[Path, Path]]:
+    """
+    将根目录下（递归）名称中含有"["、"]"的文件夹中的方括号替换为圆括号，用于对方括号路径支持不佳的旧工具。
+    从最深层开始重命名；目标文件夹已存在时跳过。
+
+    :return: (原路径, 新路径) 列表；dry_run时仅返回，不重命名
+    """
+    pairs: list[tuple[Path, Path]] = []
+    dir_paths = [p for p in walk_bms_tree(root_dir) if p.is_dir() and ("[" in p.name or "]" in p.name)]
+    for dir_path in sorted(dir_paths, key=lambda p: len(p.parts), reverse=True):
+        new_dir_path = dir_path.with_name(dir_path.name.replace("[", "(").replace("]", ")"))
+        if new_dir_path.exists():
+            print(f"Warning: Target {new_dir_path} already exists! Skipping {dir_path}")
+            continue
+        pairs.append((dir_path, new_dir_path))
+        if not dry_run:
+            shutil.move(dir_path, new_dir_path)
+    return pairs
+
+
+def replace_brackets_in_dir_names(root_dir: Path) -> None:
+    pairs = rename_brackets_to_parentheses(root_dir, dry_run=True)
+    if len(pairs) == 0:
+        print("Nothing to rename.")
+        return
+    for dir_path, new_dir_path in pairs:
+        print(f"- Ready to rename: {dir_path.relative_to(root_dir)} -> {new_dir_path.name}")
+    selection = input("Do renaming? [y/N]:")
+    if not selection.lower().startswith("y"):
+        print("Aborted.")
+        return
+    rename_brackets_to_parentheses(root_dir)
+
+
+def scan_nested_archives(root_dir: Path) -> None:
+    archive_paths = find_unpacked_nested_archives(root_dir)
+    for archive_path in archive_paths:
+        size = get_archive_uncompressed_size(archive_path)
+        size_str = f"{size // 1024} KB" if size is not None else "unknown size"
+        print(f" - {archive_path.relative_to(root_dir)} ({size_str})")
+    print(f"Found {len(archive_paths)} archive(s) to extract.")
+
+
+def rename_windows_reserved_files(root_dir: Path) -> None:
+    for path, new_path in rename_windows_reserved_filenames(root_dir, "_"):
+        print(f" - {path.relative_to(root_dir)} -> {new_path.name}")
+
+
 def remove_zero_sized_media_files(current_dir: Path, print_dir: bool = False) -> None:
     if print_dir:
         print(f"Entering dir: {current_dir}")
@@ -273,6 +379,32 @@ def remove_zero_sized_media_files(current_dir: Path, print_dir: bool = False) ->
         remove_zero_sized_media_files(current_dir=current_dir / next_dir_name, print_dir=print_dir)
 
 
+def build_collection_index(root_dir: Path) -> None:
+    """为根目录下的所有谱面建立索引数据库，保存在根目录下。"""
+    db_path = root_dir / BMS_INDEX_DB_NAME
+    count = index_bms_collection(root_dir, db_path)
+    print(f"Indexed {count} charts to {db_path}")
+
+
+def query_collection_index(db_path: Path, sql: str) -> None:
+    """对索引数据库执行SQL查询，表名为charts。"""
+    try:
+        rows = query_bms_collection(db_path, sql)
+    except (OSError, sqlite3.Error) as e:
+        print(f" !_! Query error: {e}")
+        return
+    for row in rows:
+        print(row)
+    print(f"{len(rows)} row(s).")
+
+
+def export_collection_csv(root_dir: Path) -> None:
+    """导出根目录下所有谱面的信息为CSV，保存在根目录下。"""
+    csv_path = root_dir / "bms_charts.csv"
+    count = export_bms_to_csv(root_dir, csv_path, list(BmsHeaderKey))
+    print(f"Exported {count} charts to {csv_path}")
+
+
 OPTIONS: list[Option] = [
     Option(
         set_name_by_bms,
@@ -307,6 +439,36 @@ OPTIONS: list[Option] = [
         inputs=[Input(InputType.Path, "Root Dir")],
         check_func=is_root_dir,
     ),
+    Option(
+        scan_folder_duplicate_works,
+        name="BMS根目录：按谱面内容扫描重复作品",
+        inputs=[Input(InputType.Path, "Root Dir")],
+        check_func=is_root_dir,
+    ),
+    Option(
+        strip_difficulty_suffixes,
+        name="BMS根目录：移除文件夹名末尾的难度标签",
+        inputs=[Input(InputType.Path, "Root Dir")],
+        check_func=is_root_dir,
+    ),
+    Option(
+        replace_brackets_in_dir_names,
+        name="BMS根目录：将文件夹名中的方括号替换为圆括号",
+        inputs=[Input(InputType.Path, "Root Dir")],
+        check_func=is_root_dir,
+    ),
+    Option(
+        scan_nested_archives,
+        name="BMS根目录：查找作品目录中未解压的压缩包",
+        inputs=[Input(InputType.Path, "Root Dir")],
+        check_func=is_root_dir,
+    ),
+    Option(
+        rename_windows_reserved_files,
+        name="BMS根目录：重命名Windows保留文件名（CON、NUL等）",
+        inputs=[Input(InputType.Path, "Root Dir")],
+        check_func=is_root_dir,
+    ),
     Option(
         undo_set_name,
         name="BMS根目录：撤销重命名",
@@ -319,4 +481,21 @@ OPTIONS: list[Option] = [
         inputs=[Input(InputType.Path, "Root Dir")],
         check_func=is_root_dir,
     ),
+    Option(
+        build_collection_index,
+        name="BMS根目录：建立谱面索引数据库",
+        inputs=[Input(InputType.Path, "Root Dir")],
+        check_func=is_root_dir,
+    ),
+    Option(
+        query_collection_index,
+        name="BMS根目录：使用SQL查询谱面索引数据库",
+        inputs=[Input(InputType.Path, "Index DB Path"), Input(InputType.Any, "SQL")],
+    ),
+    Option(
+        export_collection_csv,
+        name="BMS根目录：导出谱面信息为CSV",
+        inputs=[Input(InputType.Path, "Root Dir")],
+        check_func=is_root_dir,
+    ),
 ]
@@ -1,14 +1,25 @@
 import re
 import shutil
+import tomllib
+import unicodedata
 from collections.abc import Callable
+from concurrent.futures import ThreadPoolExecutor, as_completed
+from dataclasses import dataclass, field
+from enum import Enum
 from pathlib import Path
 
+from bms import BMS_FILE_EXTS, get_dir_bms_info, get_dir_encoding, parse_chart_file
+from bms.edit import group_related_bms_files_by_title, plan_bms_title_groups
+from bms.parse import match_bms_header, read_bms_file_str
+from fs import walk_bms_tree
 from fs.move import (
-    REPLACE_OPTION_UPDATE_PACK,
+    ReplaceAction,
+    ReplaceOptions,
     is_dir_having_file,
     move_elements_across_dir,
 )
-from options import Input, InputType, Option, is_not_a_dir, is_root_dir
+from fs.name import get_name_edit_distance
+from options import Input, InputType, Option, is_not_a_dir, is_root_dir, is_work_dir
 from options.bms_folder import remove_zero_sized_media_files
 
 # 日文平假名
@@ -18,7 +29,32 @@ RE_JAPANESE_KATAKANA = re.compile("[\u30a0-\u30ff]+")
 # 汉字
 RE_CHINESE_CHARACTER = re.compile("[\u4e00-\u9fa5]+")
 
-FIRST_CHAR_RULES: list[tuple[str, Callable[[str], bool]]] = [
+# 其他常见文字（按Unicode区块）
+UNICODE_SCRIPT_PATTERNS: list[tuple[str, re.Pattern[str]]] = [
+    ("希腊字母", re.compile("[\u0370-\u03ff\u1f00-\u1fff]")),
+    ("西里尔字母", re.compile("[\u0400-\u052f\u1c80-\u1c8f\u2de0-\u2dff\ua640-\ua69f]")),
+    ("希伯来字母", re.compile("[\u0590-\u05ff\ufb1d-\ufb4f]")),
+    ("阿拉伯字母", re.compile("[\u0600-\u06ff\u0750-\u077f\u08a0-\u08ff\ufb50-\ufdff\ufe70-\ufeff]")),
+    ("天城文", re.compile("[\u0900-\u097f\ua8e0-\ua8ff]")),
+    ("泰文", re.compile("[\u0e00-\u0e7f]")),
+    ("谚文", re.compile("[\u1100-\u11ff\u3130-\u318f\ua960-\ua97f\uac00-\ud7af]")),
+]
+
+
+def categorise_by_unicode_script(name: str) -> str | None:
+    """返回名称首字符所属的文字，不属于UNICODE_SCRIPT_PATTERNS中的文字时返回None"""
+    if len(name) == 0:
+        return None
+    for script_name, pattern in UNICODE_SCRIPT_PATTERNS:
+        if pattern.match(name[0]) is not None:
+            return script_name
+    return None
+
+
+# (分组名, 判断函数)
+FirstCharRule = tuple[str, Callable[[str], bool]]
+
+FIRST_CHAR_RULES: list[FirstCharRule] = [
     ("0-9", lambda name: len(name) > 0 and "0" <= name[0].upper() <= "9"),
     ("ABCD", lambda name: len(name) > 0 and "A" <= name[0].upper() <= "D"),
     ("EFGHIJK", lambda name: len(name) > 0 and "E" <= name[0].upper() <= "K"),
@@ -28,41 +64,347 @@ FIRST_CHAR_RULES: list[tuple[str, Callable[[str], bool]]] = [
     ("平假名", lambda name: len(name) > 0 and RE_JAPANESE_HIRAGANA.search(name[0]) is not None),
     ("片假名", lambda name: len(name) > 0 and RE_JAPANESE_KATAKANA.search(name[0]) is not None),
     ("汉字", lambda name: len(name) > 0 and RE_CHINESE_CHARACTER.search(name[0]) is not None),
+    *[
+        (script_name, lambda name, script_name=script_name: categorise_by_unicode_script(name) == script_name)
+        for script_name, _pattern in UNICODE_SCRIPT_PATTERNS
+    ],
     ("+", lambda name: len(name) > 0),
 ]
 
 
-def _first_char_rules_find(name: str) -> str:
-    for group_name, func in FIRST_CHAR_RULES:
+def _first_char_rules_find(name: str, rules: list[FirstCharRule] = FIRST_CHAR_RULES) -> str:
+    # NFKD：全角字母数字、半角片假名等归一为对应的常规字符
+    name = unicodedata.normalize("NFKD", name)
+    for group_name, func in rules:
         if not func(name):
             continue
         return group_name
     return "未分类"
 
 
-def split_folders_with_first_char(root_dir: Path) -> None:
-    root_folder_name = root_dir.name
+class LocaleOrder(Enum):
+    # 平假名在前（FIRST_CHAR_RULES的默认顺序）
+    HiraganaFirst = 0
+    # 片假名在前
+    KatakanaFirst = 1
+
+
+def get_first_char_rules_for_locale(
+    order: LocaleOrder, rules: list[FirstCharRule] = FIRST_CHAR_RULES
+) -> list[FirstCharRule]:
+    """按 order 调整平假名、片假名两条规则的先后，其余规则不变"""
+    rules = list(rules)
+    group_names = [group_name for group_name, _func in rules]
+    if "平假名" not in group_names or "片假名" not in group_names:
+        return rules
+    hiragana_index, katakana_index = group_names.index("平假名"), group_names.index("片假名")
+    is_hiragana_first = hiragana_index < katakana_index
+    if is_hiragana_first != (order == LocaleOrder.HiraganaFirst):
+        rules[hiragana_index], rules[katakana_index] = rules[katakana_index], rules[hiragana_index]
+    return rules
+
+
+def first_char_rules_find_locale(name: str, order: LocaleOrder) -> str:
+    return _first_char_rules_find(name, get_first_char_rules_for_locale(order))
+
+
+@dataclass
+class SplitReport:
+    # 分组（括号内的标签，或目标目录名） -> 移入该分组的作品目录名
+    moved: dict[str, list[str]] = field(default_factory=dict)
+
+
+# 拆分文件夹名称末尾的 "[标签]"
+RE_BRACKET_LABEL = re.compile(r"\s*\[([^\[\]]+)\]$")
+
+
+def _get_split_dir(root_dir: Path, label: str) -> Path:
+    return root_dir.parent / f"{root_dir.name} [{label}]"
+
+
+def _is_already_split_dir(root_dir: Path) -> bool:
+    """目录本身已是拆分结果（如 "Pack [ABCD]"）时打印原因并返回True，避免再拆分出 "Pack [ABCD] [0-9]" """
+    match = RE_BRACKET_LABEL.search(root_dir.name)
+    if match is None:
+        return False
+    print(
+        f"{root_dir} looks like an already split folder (label [{match.group(1)}]). "
+        "Splitting it again would create nested bracket folders. Aborting..."
+    )
+    return True
+
+
+def pre_create_split_dirs(root_dir: Path, rules: list[FirstCharRule] = FIRST_CHAR_RULES) -> list[Path]:
+    """预先创建各分组对应的 "{name} [{rule}]" 平级目录（已存在的跳过），返回全部分组目录路径"""
+    split_dirs: list[Path] = []
+    for group_name, _func in rules:
+        split_dir = _get_split_dir(root_dir, group_name)
+        split_dir.mkdir(exist_ok=True)
+        split_dirs.append(split_dir)
+    return split_dirs
+
+
+def _move_to_split_dir(root_dir: Path, element_name: str, label: str, report: SplitReport) -> None:
+    target_dir = _get_split_dir(root_dir, label)
+    if not target_dir.is_dir():
+        target_dir.mkdir()
+    shutil.move(root_dir / element_name, target_dir / element_name)
+    report.moved.setdefault(label, []).append(element_name)
+
+
+class ClassifyBy(Enum):
+    # 按目录名分类
+    DirName = 0
+    # 按目录中第一个谱面的#TITLE分类，适用于目录名为数字编号的情况
+    BmsTitle = 1
+
+
+def _get_classify_name(element_path: Path, classify_by: ClassifyBy) -> str:
+    if classify_by == ClassifyBy.BmsTitle and element_path.is_dir():
+        encoding = get_dir_encoding(element_path)
+        for file_path in sorted(element_path.iterdir()):
+            info = parse_chart_file(file_path, encoding)
+            if info is not None and len(info.title.strip()) > 0:
+                return info.title.strip()
+    return element_path.name
+
+
+def preview_split(
+    root_dir: Path, rules: list[FirstCharRule] = FIRST_CHAR_RULES, classify_by: ClassifyBy = ClassifyBy.DirName
+) -> dict[Path, Path]:
+    """按照首字符分类，返回各元素的 源路径 -> 目标路径。不会修改文件系统。"""
+    split_map: dict[Path, Path] = {}
+    for element_path in sorted(root_dir.iterdir()):
+        label = _first_char_rules_find(_get_classify_name(element_path, classify_by), rules)
+        split_map[element_path] = _get_split_dir(root_dir, label) / element_path.name
+    return split_map
+
+
+def split_folders_with_first_char(
+    root_dir: Path,
+    max_workers: int | None = None,
+    classify_by: ClassifyBy = ClassifyBy.DirName,
+    handle_loose_files: bool = False,
+) -> None:
+    """
+    按照首字符将大包目录中的元素移动到"{大包名} [分类]"。classify_by为BmsTitle时，按作品的#TITLE而非目录名分类。
+    各元素的移动互不依赖，使用最多 max_workers 个线程并行执行（None时使用ThreadPoolExecutor的默认值）。
+    handle_loose_files为True时，确认后先将直接位于大包目录中的BMS文件及其引用的文件按#TITLE归入新的作品目录，再进行分类。
+    """
     if not root_dir.is_dir():
         print(f"{root_dir} is not a dir! Aborting...")
         return
-    if root_folder_name.endswith("]"):
-        print(f"{root_dir} endswith ']'. Aborting...")
+    if _is_already_split_dir(root_dir):
+        return
+    split_map = preview_split(root_dir, classify_by=classify_by)
+    if handle_loose_files:
+        # 按归入作品目录之后的结构预览：散落的文件不再单独分类，而是随新的作品目录一起分类
+        plans = plan_bms_title_groups(root_dir)
+        for plan in plans:
+            print(f" - {plan.group_dir.name}: {len(plan.bms_files)} loose chart(s) will be grouped into this dir")
+            for file_path in plan.bms_files + plan.media_files + plan.shared_files:
+                split_map.pop(file_path, None)
+        for plan in plans:
+            label = _first_char_rules_find(plan.group_dir.name, FIRST_CHAR_RULES)
+            split_map[plan.group_dir] = _get_split_dir(root_dir, label) / plan.group_dir.name
+    for element_path, target_path in split_map.items():
+        print(f" - {element_path.name} -> {target_path.parent.name}")
+    confirm = input("Confirm? [y/N]")
+    if not confirm.lower().startswith("y"):
         return
+    if handle_loose_files:
+        group_related_bms_files_by_title(root_dir, dry_run=False, group_single=True)
+
+    # Create target dirs before moving in parallel
+    for target_dir in {target_path.parent for target_path in split_map.values()}:
+        if not target_dir.is_dir():
+            target_dir.mkdir()
+    # Move
+    with ThreadPoolExecutor(max_workers=max_workers) as executor:
+        futures = [
+            executor.submit(shutil.move, element_path, target_path) for element_path, target_path in split_map.items()
+        ]
+        for f in as_completed(futures):
+            try:
+                f.result()
+            except Exception as e:
+                print(f" !_! Move error: {e}")
+
+    # Remove the original folder when possible
+    if not is_dir_having_file(root_dir):
+        root_dir.rmdir()
+
+
+RE_YEAR = re.compile(r"\b(19|20)\d{2}\b")
+
+
+def _get_work_dir_comment_year(work_dir: Path) -> str | None:
+    encoding = get_dir_encoding(work_dir)
+    for file_path in work_dir.iterdir():
+        if not file_path.is_file() or not file_path.name.lower().endswith(BMS_FILE_EXTS):
+            continue
+        for line in read_bms_file_str(file_path, encoding).splitlines():
+            comment = match_bms_header(line, "COMMENT")
+            if comment is None:
+                continue
+            match = RE_YEAR.search(comment)
+            if match is not None:
+                return match.group(0)
+    return None
+
+
+def split_folders_by_year_tag(root_dir: Path) -> SplitReport:
+    """
+    按照#COMMENT中的年份（如"Made in 2019"）将作品移动到"{大包名} [年份]"。
+    找不到年份的作品移动到"{大包名} [Unknown Year]"。
+    """
+    report = SplitReport()
+    if not root_dir.is_dir():
+        print(f"{root_dir} is not a dir! Aborting...")
+        return report
+    if _is_already_split_dir(root_dir):
+        return report
+    for element_name in [p.name for p in root_dir.iterdir()]:
+        element_path = root_dir / element_name
+        if not element_path.is_dir():
+            continue
+        year = _get_work_dir_comment_year(element_path)
+        _move_to_split_dir(root_dir, element_name, year or "Unknown Year", report)
+
+    for label, names in report.moved.items():
+        print(f" - [{label}]: {len(names)} works")
+
+    # Remove the original folder when possible
+    if not is_dir_having_file(root_dir):
+        root_dir.rmdir()
+    return report
+
+
+def split_by_pack_subfolder(root_dir: Path, pack_names: list[str]) -> SplitReport:
+    """
+    根目录中混有多个大包的作品时，按照作品目录名前缀拆分：
+    以pack_names中的前缀开头的作品移动到同级的"{前缀} [songs]"，
+    其余作品移动到"{根目录名} [Other]"。前缀按给定顺序匹配。
+    """
+    report = SplitReport()
+    if not root_dir.is_dir():
+        print(f"{root_dir} is not a dir! Aborting...")
+        return report
+    if _is_already_split_dir(root_dir):
+        return report
     parent_dir = root_dir.parent
     for element_name in [p.name for p in root_dir.iterdir()]:
         element_path = root_dir / element_name
-        # Find target dir
-        rule = _first_char_rules_find(element_name)
-        target_dir = parent_dir / f"{root_folder_name} [{rule}]"
+        if not element_path.is_dir():
+            continue
+        pack_name = next((name for name in pack_names if element_name.startswith(name)), None)
+        target_dir = parent_dir / f"{pack_name} [songs]" if pack_name is not None else _get_split_dir(root_dir, "Other")
         if not target_dir.is_dir():
             target_dir.mkdir()
-        # Move
-        target_path = target_dir / element_name
-        shutil.move(element_path, target_path)
+        shutil.move(element_path, target_dir / element_name)
+        report.moved.setdefault(target_dir.name, []).append(element_name)
+
+    for label, names in report.moved.items():
+        print(f" - {label}: {len(names)} works")
+
+    # Remove the original folder when possible
+    if not is_dir_having_file(root_dir):
+        root_dir.rmdir()
+    return report
+
+
+def split_by_bms_genre_prefix(root_dir: Path, prefixes: list[tuple[str, str]]) -> SplitReport:
+    """
+    按照#GENRE前缀拆分主题子包：prefixes中每项为 (GENRE前缀, 括号标签)，
+    #GENRE以该前缀开头（不区分大小写）的作品移动到"{大包名} [标签]"。前缀按给定顺序匹配，未匹配的作品保持不动。
+    """
+    report = SplitReport()
+    if not root_dir.is_dir():
+        print(f"{root_dir} is not a dir! Aborting...")
+        return report
+    if _is_already_split_dir(root_dir):
+        return report
+    for element_name in [p.name for p in root_dir.iterdir()]:
+        element_path = root_dir / element_name
+        if not element_path.is_dir():
+            continue
+        info = get_dir_bms_info(element_path)
+        if info is None:
+            continue
+        genre = info.genre.strip().lower()
+        label = next((label for prefix, label in prefixes if genre.startswith(prefix.lower())), None)
+        if label is not None:
+            _move_to_split_dir(root_dir, element_name, label, report)
+
+    for label, names in report.moved.items():
+        print(f" - [{label}]: {len(names)} works")
+    return report
+
+
+class SplitBalance(Enum):
+    # 两边作品数量相同（按名称排序后对半分）
+    EqualCount = 0
+    # 两边总大小尽量接近
+    EqualSize = 1
+
+
+def _get_dir_size(dir_path: Path) -> int:
+    return sum(p.stat().st_size for p in walk_bms_tree(dir_path) if p.is_file())
+
+
+def split_pack_into_ab(root_dir: Path, balance: SplitBalance) -> tuple[Path, Path] | None:
+    """
+    将大包中的作品分成两半，移动到同级的"{大包名} [A]"和"{大包名} [B]"，用于两台机器分别放置。
+
+    :return: (A目录, B目录)，未进行拆分时为None
+    """
+    if not root_dir.is_dir():
+        print(f"{root_dir} is not a dir! Aborting...")
+        return None
+    if _is_already_split_dir(root_dir):
+        return None
+    work_names = sorted(p.name for p in root_dir.iterdir() if p.is_dir())
+    a_names: list[str] = []
+    b_names: list[str] = []
+    match balance:
+        case SplitBalance.EqualCount:
+            half = (len(work_names) + 1) // 2
+            a_names, b_names = work_names[:half], work_names[half:]
+        case SplitBalance.EqualSize:
+            # 从大到小，依次放入当前总大小较小的一边
+            sizes = {name: _get_dir_size(root_dir / name) for name in work_names}
+            a_size, b_size = 0, 0
+            for name in sorted(work_names, key=lambda name: sizes[name], reverse=True):
+                if a_size <= b_size:
+                    a_names.append(name)
+                    a_size += sizes[name]
+                else:
+                    b_names.append(name)
+                    b_size += sizes[name]
+
+    report = SplitReport()
+    for label, names in (("A", a_names), ("B", b_names)):
+        _get_split_dir(root_dir, label).mkdir(exist_ok=True)
+        for name in names:
+            _move_to_split_dir(root_dir, name, label, report)
+        print(f" - [{label}]: {len(names)} works")
 
     # Remove the original folder when possible
     if not is_dir_having_file(root_dir):
         root_dir.rmdir()
+    return _get_split_dir(root_dir, "A"), _get_split_dir(root_dir, "B")
+
+
+def split_folders_with_first_char_of_title(root_dir: Path) -> None:
+    split_folders_with_first_char(root_dir, classify_by=ClassifyBy.BmsTitle)
+
+
+def split_folders_with_first_char_including_loose_files(root_dir: Path) -> None:
+    split_folders_with_first_char(root_dir, handle_loose_files=True)
+
+
+def split_pack_into_ab_by_size(root_dir: Path) -> None:
+    split_pack_into_ab(root_dir, SplitBalance.EqualSize)
 
 
 def undo_split_pack(root_dir: Path) -> None:
@@ -83,6 +425,56 @@ def undo_split_pack(root_dir: Path) -> None:
         move_elements_across_dir(from_dir, to_dir)
 
 
+def reconstruct_split_pack_from_fragments(fragment_dirs: list[Path], target_dir: Path) -> int:
+    """
+    将分散在多个位置（如不同硬盘）的拆分文件夹（名称以"]"结尾）中的作品合并到target_dir。
+    跨硬盘时由shutil.move自动改为复制后删除。
+
+    :return: 合并的作品目录数量
+    """
+    work_count = 0
+    for fragment_dir in fragment_dirs:
+        if not fragment_dir.is_dir() or not fragment_dir.name.endswith("]"):
+            print(f" !_! {fragment_dir} is not a split folder, skipping.")
+            continue
+        work_count += len([p for p in fragment_dir.iterdir() if p.is_dir()])
+        print(f" - {target_dir} <- {fragment_dir}")
+        move_elements_across_dir(fragment_dir, target_dir, replace_options=ReplaceOptions.update_pack())
+    return work_count
+
+
+# 复制产生的后缀，如 "Pack [0-9] (1)"
+RE_COPY_SUFFIX = re.compile(r"\s*\(\d+\)$")
+
+
+def deduplicate_identical_bracket_folders(root_dir: Path) -> int:
+    """
+    合并根目录下重复的拆分文件夹：Unicode规范化（NFC）后名称相同的文件夹，
+    以及 "Pack [0-9]" 与 "Pack [0-9] (1)" 这样由复制产生的文件夹。
+    重复的文件夹合并到规范名称的文件夹中（不存在时为排序后的第一个）。
+
+    :return: 被合并的文件夹数量
+    """
+    groups: dict[str, list[Path]] = {}
+    for dir_path in sorted(p for p in root_dir.iterdir() if p.is_dir()):
+        key = RE_COPY_SUFFIX.sub("", unicodedata.normalize("NFC", dir_path.name))
+        if key.endswith("]"):
+            groups.setdefault(key, []).append(dir_path)
+
+    merged_count = 0
+    for key, dir_paths in groups.items():
+        if len(dir_paths) < 2:
+            continue
+        target_dir = next((p for p in dir_paths if p.name == key), dir_paths[0])
+        for dir_path in dir_paths:
+            if dir_path == target_dir:
+                continue
+            print(f" - {target_dir.name} <- {dir_path.name}")
+            move_elements_across_dir(dir_path, target_dir, replace_options=ReplaceOptions.update_pack())
+            merged_count += 1
+    return merged_count
+
+
 def merge_split_folders(root_dir: Path) -> None:
     dir_names: list[str] = [p.name for p in root_dir.iterdir() if p.is_dir()]
 
@@ -146,37 +538,113 @@ def merge_split_folders(root_dir: Path) -> None:
         move_elements_across_dir(from_dir_path, target_dir_path)
 
 
-def move_works_in_pack(root_dir_from: Path, root_dir_to: Path) -> None:
+# (源作品目录, 已存在的目标作品目录) -> 处理方式
+ConflictResolver = Callable[[Path, Path], ReplaceAction]
+
+
+def _get_conflict_replace_options(action: ReplaceAction) -> ReplaceOptions:
+    if action == ReplaceAction.CheckReplace:
+        return ReplaceOptions.update_pack()
+    return ReplaceOptions(default=action)
+
+
+def rename_colliding_work_dirs(source_dir: Path, dest_dir: Path) -> list[tuple[str, str]]:
+    """
+    对于源目录中与目标目录重名的子文件夹，在源目录中为其追加 "_2"、"_3" 等后缀，使其不再冲突。
+
+    :return: (原名称, 新名称) 列表
+    """
+    renames: list[tuple[str, str]] = []
+    for dir_path in sorted(p for p in source_dir.iterdir() if p.is_dir()):
+        if not (dest_dir / dir_path.name).exists():
+            continue
+        index = 2
+        while (dest_dir / f"{dir_path.name}_{index}").exists() or (source_dir / f"{dir_path.name}_{index}").exists():
+            index += 1
+        new_name = f"{dir_path.name}_{index}"
+        print(f" - Rename: {dir_path.name} -> {new_name}")
+        dir_path.rename(source_dir / new_name)
+        renames.append((dir_path.name, new_name))
+    return renames
+
+
+def _find_bracket_dir(root_dir: Path, label: str) -> Path | None:
+    for dir_path in sorted(p for p in root_dir.iterdir() if p.is_dir()):
+        match = RE_BRACKET_LABEL.search(dir_path.name)
+        if match is not None and match.group(1) == label:
+            return dir_path
+    return None
+
+
+def move_works_in_pack(
+    root_dir_from: Path,
+    root_dir_to: Path,
+    conflict_resolver: ConflictResolver | None = None,
+    rename_on_conflict: bool = False,
+    flatten_bracket_dirs: bool = False,
+) -> None:
+    """
+    conflict_resolver：目标作品目录已存在时调用，返回合并时使用的ReplaceAction。
+    未指定时使用ReplaceOptions.update_pack()。
+    rename_on_conflict：为True时，不合并重名作品，而是先用 rename_colliding_work_dirs 为源作品目录改名。
+    flatten_bracket_dirs：为True时，源目录中的 "xxx [标签]" 子文件夹视为拆分文件夹，
+    其中的作品移入目标目录中标签相同的子文件夹（不存在时直接移入目标目录），而不是作为一个作品合并。
+    """
     if root_dir_from == root_dir_to:
         return
+    bracket_dir_count = 0
+    if flatten_bracket_dirs:
+        for bracket_dir in sorted(p for p in root_dir_from.iterdir() if p.is_dir()):
+            match = RE_BRACKET_LABEL.search(bracket_dir.name)
+            if match is None:
+                continue
+            dst_bracket_dir = _find_bracket_dir(root_dir_to, match.group(1)) or root_dir_to
+            print(f"Moving bracket dir: {bracket_dir.name} -> {dst_bracket_dir.name}")
+            move_works_in_pack(bracket_dir, dst_bracket_dir, conflict_resolver, rename_on_conflict)
+            bracket_dir_count += 1
+            # 不含作品子目录时，拆分文件夹会作为一个整体被合并并移除
+            if bracket_dir.exists() and not is_dir_having_file(bracket_dir):
+                bracket_dir.rmdir()
+    if rename_on_conflict:
+        rename_colliding_work_dirs(root_dir_from, root_dir_to)
     move_count = 0
     for bms_dir_name in [p.name for p in root_dir_from.iterdir()]:
         bms_dir = root_dir_from / bms_dir_name
         if not bms_dir.is_dir():
             continue
+        if flatten_bracket_dirs and RE_BRACKET_LABEL.search(bms_dir_name) is not None:
+            # 拆分文件夹中剩余的文件，不作为作品合并，以免重新产生嵌套的拆分文件夹
+            print(f" !_! {bms_dir_name} still has files left, skipping.")
+            continue
 
         print(f"Moving: {bms_dir_name}")
 
         dst_bms_dir = root_dir_to / bms_dir_name
+        replace_options = ReplaceOptions.update_pack()
+        if conflict_resolver is not None and dst_bms_dir.is_dir():
+            replace_options = _get_conflict_replace_options(conflict_resolver(bms_dir, dst_bms_dir))
         move_elements_across_dir(
             bms_dir,
             dst_bms_dir,
-            replace_options=REPLACE_OPTION_UPDATE_PACK,
+            replace_options=replace_options,
         )
         move_count += 1
     if move_count > 0:
         print(f"Move {move_count} songs.")
         return
+    if bracket_dir_count > 0:
+        return
 
     # Deal with song dir
     move_elements_across_dir(
         root_dir_from,
         root_dir_to,
-        replace_options=REPLACE_OPTION_UPDATE_PACK,
+        replace_options=ReplaceOptions.update_pack(),
     )
 
 
-def _workdir_remove_unneed_media_files(work_dir: Path, rule: list[tuple[list[str], list[str]]]) -> None:
+def _get_remove_media_pairs(work_dir: Path, rule: list[tuple[list[str], list[str]]]) -> list[tuple[Path, Path]]:
+    """:return: (保留的文件, 将被移除的文件) 列表"""
     remove_pairs: list[tuple[Path, Path]] = []
     removed_files: set[Path] = set()
     for file_name in [p.name for p in work_dir.iterdir()]:
@@ -202,7 +670,11 @@ def _workdir_remove_unneed_media_files(work_dir: Path, rule: list[tuple[list[str
                     continue
                 remove_pairs.append((check_file_path, replacing_file_path))
                 removed_files.add(replacing_file_path)
+    return remove_pairs
+
 
+def _workdir_remove_unneed_media_files(work_dir: Path, rule: list[tuple[list[str], list[str]]]) -> None:
+    remove_pairs = _get_remove_media_pairs(work_dir, rule)
     if len(remove_pairs) > 0:
         print(f"Entering: {work_dir}")
 
@@ -247,14 +719,55 @@ REMOVE_MEDIA_RULE_WAV_FILL_FLAC: list[tuple[list[str], list[str]]] = [
 REMOVE_MEDIA_RULE_MPG_FILL_WMV: list[tuple[list[str], list[str]]] = [
     (["mpg"], ["wmv"]),
 ]
+REMOVE_MEDIA_RULE_WEBM_OVER_MP4: list[tuple[list[str], list[str]]] = [
+    (["webm"], ["mp4"]),
+]
+REMOVE_MEDIA_RULE_MP4_OVER_WEBM: list[tuple[list[str], list[str]]] = [
+    (["mp4"], ["webm"]),
+]
 
 REMOVE_MEDIA_FILE_RULES: list[list[tuple[list[str], list[str]]]] = [
     REMOVE_MEDIA_RULE_ORAJA,
     REMOVE_MEDIA_RULE_WAV_FILL_FLAC,
     REMOVE_MEDIA_RULE_MPG_FILL_WMV,
+    REMOVE_MEDIA_RULE_WEBM_OVER_MP4,
+    REMOVE_MEDIA_RULE_MP4_OVER_WEBM,
 ]
 
 
+def load_media_rules_from_toml(path: Path) -> list[tuple[list[str], list[str]]]:
+    """
+    从TOML文件读取自定义规则，格式：
+
+        [[rules]]
+        upper = ["mp4"]
+        lower = ["avi", "wmv"]
+
+    :raises ValueError: 格式错误，或扩展名不是小写ASCII
+    """
+    with path.open("rb") as f:
+        try:
+            config = tomllib.load(f)
+        except tomllib.TOMLDecodeError as e:
+            raise ValueError(f"{path}: {e}") from e
+    rules = config.get("rules")
+    if not isinstance(rules, list) or len(rules) == 0:
+        raise ValueError(f"{path}: expected at least one [[rules]] table")
+    result: list[tuple[list[str], list[str]]] = []
+    for i, rule in enumerate(rules):
+        exts_pair: list[list[str]] = []
+        for key in ("upper", "lower"):
+            exts = rule.get(key) if isinstance(rule, dict) else None
+            if not isinstance(exts, list) or len(exts) == 0:
+                raise ValueError(f"{path}: rules[{i}].{key} must be a non-empty array")
+            for ext in exts:
+                if not isinstance(ext, str) or not ext.isascii() or not ext.isalnum() or ext != ext.lower():
+                    raise ValueError(f"{path}: rules[{i}].{key}: {ext!r} is not a lowercase ASCII extension")
+            exts_pair.append(exts)
+        result.append((exts_pair[0], exts_pair[1]))
+    return result
+
+
 def remove_unneed_media_files(root_dir: Path, rule: list[tuple[list[str], list[str]]] | None = None) -> None:
     # Select Preset
     if rule is None:
@@ -262,11 +775,19 @@ def remove_unneed_media_files(root_dir: Path, rule: list[tuple[list[str], list[s
     if len(rule) == 0:
         for i, _rule in enumerate(REMOVE_MEDIA_FILE_RULES):
             print(f"- {i}: {REMOVE_MEDIA_FILE_RULES[i]}")
-        selection_str = input("Select Preset (Default: 0):")
-        selection = 0
-        if len(selection_str) > 0:
-            selection = int(selection_str)
-        rule = REMOVE_MEDIA_FILE_RULES[selection]
+        selection_str = input("Select Preset, or input a .toml rule file path (Default: 0):")
+        while selection_str.strip().lower().endswith(".toml"):
+            try:
+                rule = load_media_rules_from_toml(Path(selection_str.strip()))
+                break
+            except (OSError, ValueError, tomllib.TOMLDecodeError) as e:
+                print(f" !_! Failed to load rule file: {e}")
+            selection_str = input("Select Preset, or input a .toml rule file path (Default: 0):")
+        if len(rule) == 0:
+            selection = 0
+            if len(selection_str) > 0:
+                selection = int(selection_str)
+            rule = REMOVE_MEDIA_FILE_RULES[selection]
     print(f"Selected: {rule}")
 
     # Do
@@ -280,25 +801,51 @@ def remove_unneed_media_files(root_dir: Path, rule: list[tuple[list[str], list[s
         )
 
 
-def move_out_works(target_root_dir: Path) -> None:
-    for root_dir_name in [p.name for p in target_root_dir.iterdir()]:
-        root_dir_path = target_root_dir / root_dir_name
-        if not root_dir_path.is_dir():
-            continue
-        for work_dir_name in [p.name for p in root_dir_path.iterdir()]:
-            work_dir_path = root_dir_path / work_dir_name
-            target_work_dir_path = target_root_dir / work_dir_name
-            # Deal with song dir
-            move_elements_across_dir(
-                work_dir_path,
-                target_work_dir_path,
-                replace_options=REPLACE_OPTION_UPDATE_PACK,
-            )
-        if not is_dir_having_file(root_dir_path):
-            root_dir_path.rmdir()
-
-
-def move_works_with_same_name(root_dir_from: Path, root_dir_to: Path) -> None:
+def estimate_remove_media_bytes(root_dir: Path, rule: list[tuple[list[str], list[str]]]) -> int:
+    """
+    模拟 remove_unneed_media_files 的移除逻辑（不删除文件），统计将被移除的文件总大小。
+
+    :return: 可释放的字节数
+    """
+    total_bytes = 0
+    for bms_dir_path in [p for p in root_dir.iterdir() if p.is_dir()]:
+        for _, replacing_file_path in _get_remove_media_pairs(bms_dir_path, rule):
+            total_bytes += replacing_file_path.stat().st_size
+    return total_bytes
+
+
+def move_out_works(target_root_dir: Path, depth: int = 1) -> None:
+    """
+    将子文件夹中的作品移出到根目录。
+    depth大于1时，对移出后仍不含谱面文件、且含有子文件夹的目录继续移出，最多depth层。
+    """
+    pack_dirs = [p for p in target_root_dir.iterdir() if p.is_dir()]
+    for _ in range(depth):
+        moved_dirs: list[Path] = []
+        for root_dir_path in pack_dirs:
+            for work_dir_name in [p.name for p in root_dir_path.iterdir()]:
+                work_dir_path = root_dir_path / work_dir_name
+                target_work_dir_path = target_root_dir / work_dir_name
+                if work_dir_path.is_dir():
+                    moved_dirs.append(target_work_dir_path)
+                # Deal with song dir
+                move_elements_across_dir(
+                    work_dir_path,
+                    target_work_dir_path,
+                    replace_options=ReplaceOptions.update_pack(),
+                )
+            if not is_dir_having_file(root_dir_path):
+                root_dir_path.rmdir()
+        pack_dirs = [
+            p
+            for p in moved_dirs
+            if p.is_dir() and not is_work_dir(p) and any(sub.is_dir() for sub in p.iterdir())
+        ]
+        if len(pack_dirs) == 0:
+            break
+
+
+def move_works_with_same_name(root_dir_from: Path, root_dir_to: Path, fuzzy_threshold: int | None = None) -> None:
     """
     将源文件夹(dir_from)中的子文件夹合并到目标文件夹(dir_to)中的对应子文件夹
 
@@ -311,6 +858,7 @@ def move_works_with_same_name(root_dir_from: Path, root_dir_to: Path) -> None:
     参数:
         dir_from (Path): 源文件夹路径
         dir_to (Path): 目标文件夹路径
+        fuzzy_threshold (int | None): 设置时，未精确匹配的A还会匹配与其编辑距离不超过该值的B（取最接近者）
     """
 
     # 验证输入路径是否存在且为目录
@@ -326,6 +874,7 @@ def move_works_with_same_name(root_dir_from: Path, root_dir_to: Path) -> None:
     to_subdirs: list[str] = [d for d in [p.name for p in root_dir_to.iterdir()] if (root_dir_to / d).is_dir()]
 
     pairs: list[tuple[str, Path, str, Path]] = []
+    fuzzy_pairs: list[tuple[str, Path, str, Path]] = []
 
     # 遍历源目录的每个子文件夹
     for from_dir_name in from_subdirs:
@@ -337,20 +886,31 @@ def move_works_with_same_name(root_dir_from: Path, root_dir_to: Path) -> None:
                 to_dir_path: Path = root_dir_to / to_dir_name
                 pairs.append((from_dir_name, from_dir_path, to_dir_name, to_dir_path))
                 break
+        else:
+            if fuzzy_threshold is None or len(to_subdirs) == 0:
+                continue
+            # 模糊匹配：取编辑距离最小的目标子文件夹
+            distance, to_dir_name = min((get_name_edit_distance(from_dir_name, name), name) for name in to_subdirs)
+            if distance <= fuzzy_threshold:
+                fuzzy_pairs.append((from_dir_name, from_dir_path, to_dir_name, root_dir_to / to_dir_name))
 
     for from_dir_name, _from_dir_path, to_dir_name, _target_path in pairs:
         print(f" -> {from_dir_name} => {to_dir_name}")
+    if len(fuzzy_pairs) > 0:
+        print("模糊匹配（请仔细核对）：")
+        for from_dir_name, _from_dir_path, to_dir_name, _target_path in fuzzy_pairs:
+            print(f" ~> {from_dir_name} => {to_dir_name}")
     selection = input("是否合并？[y/N]")
     if not selection.lower().startswith("y"):
         return
 
     # 将源文件夹内容合并到每个匹配的目标文件夹
-    for _, from_dir_path, _, target_path in pairs:
+    for _, from_dir_path, _, target_path in pairs + fuzzy_pairs:
         print(f"合并: '{from_dir_path}' -> '{target_path}'")
         move_elements_across_dir(
             from_dir_path,
             target_path,
-            replace_options=REPLACE_OPTION_UPDATE_PACK,
+            replace_options=ReplaceOptions.update_pack(),
         )
 
 
@@ -412,7 +972,7 @@ def move_works_with_same_name_to_siblings(root_dir_from: Path) -> None:
         move_elements_across_dir(
             from_dir_path,
             target_path,
-            replace_options=REPLACE_OPTION_UPDATE_PACK,
+            replace_options=ReplaceOptions.update_pack(),
         )
 
 
@@ -423,6 +983,30 @@ OPTIONS: list[Option] = [
         inputs=[Input(InputType.Path, "")],
         check_func=is_root_dir,
     ),
+    Option(
+        split_folders_with_first_char_of_title,
+        name="BMS大包目录：将该目录下的作品，按照#TITLE的首字符分成多个文件夹",
+        inputs=[Input(InputType.Path, "")],
+        check_func=is_root_dir,
+    ),
+    Option(
+        split_folders_with_first_char_including_loose_files,
+        name="BMS大包目录：将该目录下的作品及散落的谱面文件（先按#TITLE归入作品文件夹），按照首字符分成多个文件夹",
+        inputs=[Input(InputType.Path, "")],
+        check_func=is_root_dir,
+    ),
+    Option(
+        split_folders_by_year_tag,
+        name="BMS大包目录：将该目录下的作品，按照#COMMENT中的年份分成多个文件夹",
+        inputs=[Input(InputType.Path, "")],
+        check_func=is_root_dir,
+    ),
+    Option(
+        split_pack_into_ab_by_size,
+        name="BMS大包目录：将该目录下的作品，按总大小平均分成[A]、[B]两个文件夹",
+        inputs=[Input(InputType.Path, "")],
+        check_func=is_root_dir,
+    ),
     Option(
         undo_split_pack,
         name="BMS大包目录：（撤销操作）将该目录下的作品，按照首字符分成多个文件夹",
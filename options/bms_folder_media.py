@@ -1,7 +1,20 @@
 from pathlib import Path
 
 from bms import AUDIO_FILE_EXTS, VIDEO_FILE_EXTS
-from media.audio import AUDIO_PRESETS, bms_folder_transfer_audio
+from bms.edit import replace_bms_file_references_ext
+from media.audio import (
+    AUDIO_PRESETS,
+    CompressionReport,
+    bms_folder_transfer_audio,
+    check_flac_integrity,
+    compress_pack_wav_with_flac,
+)
+from media.image import (
+    auto_convert_bmp_to_png,
+    batch_crop_stage_images,
+    generate_missing_stage_file,
+    scan_for_large_uncompressed_bmp,
+)
 from media.video import VIDEO_PRESETS, bms_folder_transfer_video
 from options import (
     Input,
@@ -72,6 +85,60 @@ def transfer_video(root_dir: Path) -> None:
     )
 
 
+def check_flac_files(root_dir: Path) -> None:
+    corrupted_count = 0
+    for bms_dir_path in [p for p in root_dir.iterdir() if p.is_dir()]:
+        for file_path, error in check_flac_integrity(bms_dir_path):
+            print(f" !_! {file_path}: {error}")
+            corrupted_count += 1
+    print(f"Found {corrupted_count} corrupted FLAC file(s).")
+
+
+def convert_large_bmp_files(root_dir: Path, size_limit_kb: int) -> None:
+    bmp_files = scan_for_large_uncompressed_bmp(root_dir, size_limit_kb * 1024)
+    if len(bmp_files) == 0:
+        print("No large BMP file found.")
+        return
+    for file_path, file_size in bmp_files:
+        print(f" - {file_path} ({file_size // 1024} KB)")
+    selection = input(f"Convert {len(bmp_files)} file(s) to PNG? [y/N]: ")
+    if not selection.lower().startswith("y"):
+        return
+    for file_path, _file_size in bmp_files:
+        auto_convert_bmp_to_png(file_path)
+
+
+def generate_missing_stage_files(root_dir: Path) -> None:
+    fixed_count = 0
+    for bms_dir_path in [p for p in root_dir.iterdir() if p.is_dir()]:
+        if generate_missing_stage_file(bms_dir_path, Path("ffmpeg"), (640, 480)):
+            fixed_count += 1
+    print(f"Fixed stage file in {fixed_count} work dir(s).")
+
+
+def resize_stage_images(root_dir: Path) -> None:
+    resized_count = batch_crop_stage_images(root_dir, Path("ffmpeg"), (640, 480))
+    print(f"Resized {resized_count} stage image(s).")
+
+
+def _update_bms_wav_references_to_flac(report: CompressionReport) -> None:
+    # 每个作品目录的BMS文件只改写一次
+    for work_dir, wav_paths in report.compressed.items():
+        wav_names = [wav_path.relative_to(work_dir).as_posix() for wav_path in wav_paths]
+        replace_bms_file_references_ext(work_dir, wav_names, ".flac")
+
+
+def compress_wav_with_flac(root_dir: Path) -> None:
+    report = compress_pack_wav_with_flac(root_dir, Path("flac"), delete_original=True)
+    _update_bms_wav_references_to_flac(report)
+    files_compressed = sum(len(wav_paths) for wav_paths in report.compressed.values())
+    print(f"Compressed {files_compressed} file(s), saved {report.bytes_saved // 1024} KB.")
+    if report.bytes_grown > 0:
+        print(f" !_! Some FLAC files are larger than their WAV, grown {report.bytes_grown // 1024} KB.")
+    for file_path in report.failed:
+        print(f" !_! Failed: {file_path}")
+
+
 OPTIONS = [
     Option(
         func=transfer_audio,
@@ -89,4 +156,45 @@ OPTIONS = [
         ],
         check_func=[is_root_dir, check_ffmpeg_exec],
     ),
+    Option(
+        func=check_flac_files,
+        name="BMS根目录：检查FLAC文件完整性",
+        inputs=[
+            Input(InputType.Path, "Root Dir"),
+        ],
+        check_func=[is_root_dir, check_flac_exec],
+    ),
+    Option(
+        func=convert_large_bmp_files,
+        name="BMS根目录：将大体积BMP图片转换为PNG",
+        inputs=[
+            Input(InputType.Path, "Root Dir"),
+            Input(InputType.Int, "Size Limit (KB):"),
+        ],
+        check_func=[lambda root_dir, *_args: is_root_dir(root_dir), check_ffmpeg_exec],
+    ),
+    Option(
+        func=generate_missing_stage_files,
+        name="BMS根目录：为缺少STAGEFILE的作品生成占位图片",
+        inputs=[
+            Input(InputType.Path, "Root Dir"),
+        ],
+        check_func=[is_root_dir, check_ffmpeg_exec],
+    ),
+    Option(
+        func=resize_stage_images,
+        name="BMS根目录：将STAGEFILE图片统一缩放为640×480",
+        inputs=[
+            Input(InputType.Path, "Root Dir"),
+        ],
+        check_func=[is_root_dir, check_ffmpeg_exec],
+    ),
+    Option(
+        func=compress_wav_with_flac,
+        name="BMS根目录：将WAV压缩为FLAC，并更新谱面中的引用",
+        inputs=[
+            Input(InputType.Path, "Root Dir"),
+        ],
+        check_func=[is_root_dir, check_flac_exec],
+    ),
 ]
@@ -4,6 +4,7 @@ from pathlib import Path
 from fs.move import is_dir_having_file, move_elements_across_dir
 from fs.rawpack import (
     get_num_set_file_names,
+    is_zip_password_protected,
     move_out_files_in_folder_in_cache_dir,
     unzip_file_to_cache_dir,
 )
@@ -29,6 +30,9 @@ def unzip_numeric_to_bms_folder(pack_dir: Path, cache_dir: Path, root_dir: Path,
     for file_name in num_set_file_names:
         file_path = pack_dir / file_name
         id_str = file_name.split(" ")[0]
+        if file_path.suffix.lower() == ".zip" and is_zip_password_protected(file_path):
+            print(f" !_! {file_name} is password protected, skipping.")
+            continue
 
         # Prepare an empty cache dir
         cache_dir_path = cache_dir / id_str
@@ -105,6 +109,9 @@ def unzip_with_name_to_bms_folder(pack_dir: Path, cache_dir: Path, root_dir: Pat
 
     for file_name in num_set_file_names:
         file_path = pack_dir / file_name
+        if file_path.suffix.lower() == ".zip" and is_zip_password_protected(file_path):
+            print(f" !_! {file_name} is password protected, skipping.")
+            continue
         # 获取文件名（不含扩展名），并移除末尾的所有点号
         file_name_without_ext = file_path.stem
         file_name_without_ext = file_name_without_ext.rstrip(".")
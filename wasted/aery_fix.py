@@ -1,7 +1,7 @@
 from pathlib import Path
 
 from fs import bms_dir_similarity
-from fs.move import REPLACE_OPTION_UPDATE_PACK, move_elements_across_dir
+from fs.move import ReplaceOptions, move_elements_across_dir
 from options import input_path
 
 
@@ -50,7 +50,7 @@ def main() -> None:
         move_elements_across_dir(
             p_from,
             p_to,
-            replace_options=REPLACE_OPTION_UPDATE_PACK,
+            replace_options=ReplaceOptions.update_pack(),
         )
 
 